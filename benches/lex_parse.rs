@@ -0,0 +1,102 @@
+use monkey_lang_rust::build_tools::lexer::Lexer;
+use monkey_lang_rust::build_tools::parser::Parser;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Size of the generated script
+const SCRIPT_BYTES: usize = 4 * 1024 * 1024;
+
+/// How many times each benchmark runs. The fastest run is reported, being the one least disturbed
+/// by anything else happening on the machine
+const RUNS: usize = 10;
+
+/// Run with `cargo bench`. Prints the lexing and parsing throughput on a generated script of a
+/// few megabytes that mixes every kind of statement, expression and literal
+fn main() {
+    let script = script(SCRIPT_BYTES);
+    let token_count = Lexer::new(&script).count();
+
+    println!(
+        "script: {:.1} MiB, {} lines, {} tokens",
+        mib(script.len()),
+        script.lines().count(),
+        token_count
+    );
+
+    let lex = fastest(|| {
+        black_box(Lexer::new(black_box(&script)).count());
+    });
+    report("lex", lex, script.len(), token_count);
+
+    let parse = fastest(|| {
+        let mut parser = Parser::new(Lexer::new(black_box(&script)));
+        let program = parser.parse_program();
+        assert!(
+            parser.errors().is_empty(),
+            "the generated script should parse"
+        );
+        black_box(program);
+    });
+    report("lex + parse", parse, script.len(), token_count);
+}
+
+/// script generates at least `bytes` of valid Monkey. Every chunk uses fresh names, so the
+/// identifiers are as varied as in a real program rather than a handful repeated
+fn script(bytes: usize) -> String {
+    let mut script = String::with_capacity(bytes + 1024);
+    let mut n = 0;
+
+    while script.len() < bytes {
+        script += &format!(
+            r#"/// Computes a value for chunk {n}
+let value_{n} = {n} * 2 + (3 - 1) % 7;
+const limit_{n} = 0xff_{n:x} + 0b1010 - 1_000;
+let ratio_{n} = 3.14159 * value_{n} / 2.5e3;
+let label_{n} = "chunk {n}\t" + "done";
+let fib_{n} = func(x) {{
+    if (x < 2 && x >= 0 || false) {{
+        return x;
+    }} else {{
+        fib_{n}(x - 1) + fib_{n}(x - 2)
+    }}
+}};
+let list_{n} = [value_{n}, limit_{n}, ratio_{n}, fib_{n}(10), !true, -value_{n}];
+let table_{n} = {{"value": list_{n}[0], "limit": list_{n}[1], {n}: label_{n}}};
+/* block comment */ table_{n}["value"] == list_{n}[2 - 2] != (value_{n} <= limit_{n});
+"#,
+            n = n
+        );
+        n += 1;
+    }
+
+    script
+}
+
+/// fastest runs `f` RUNS times and returns the fastest run
+fn fastest(mut f: impl FnMut()) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn report(name: &str, elapsed: Duration, bytes: usize, tokens: usize) {
+    let seconds = elapsed.as_secs_f64();
+
+    println!(
+        "{:<12} {:>9.2} ms {:>9.1} MiB/s {:>7.1} M tokens/s",
+        name,
+        seconds * 1000.0,
+        mib(bytes) / seconds,
+        tokens as f64 / seconds / 1_000_000.0
+    );
+}
+
+fn mib(bytes: usize) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
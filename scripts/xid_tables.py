@@ -0,0 +1,68 @@
+#!/usr/bin/env python3
+"""Generates src/build_tools/unicode/tables.rs, the XID_Start and XID_Continue tables the lexer
+uses to recognize identifiers.
+
+The properties come from the Unicode data bundled with the regex package (pip install regex),
+which should be the same Unicode version as the Rust toolchain's char::UNICODE_VERSION. Pass that
+version along so it is recorded in the generated file:
+
+    python3 scripts/xid_tables.py 17.0.0 > src/build_tools/unicode/tables.rs
+"""
+
+import sys
+
+import regex
+
+RANGES_PER_LINE = 3
+
+XID_START_DOC = ["Characters with the XID_Start property, which can begin an identifier"]
+XID_CONTINUE_DOC = [
+    "Characters with the XID_Continue property, which can appear after the first character",
+    "of an identifier. A superset of XID_START",
+]
+
+
+def ranges(prop):
+    """Returns the code points with the binary property `prop` as sorted (first, last) ranges"""
+    pattern = regex.compile(r"\p{%s}" % prop)
+    result = []
+
+    for cp in range(0x110000):
+        # Surrogates aren't chars in Rust, and never have these properties anyway
+        if 0xD800 <= cp <= 0xDFFF or not pattern.fullmatch(chr(cp)):
+            continue
+        if result and result[-1][1] == cp - 1:
+            result[-1][1] = cp
+        else:
+            result.append([cp, cp])
+
+    return result
+
+
+def table(name, doc, prop):
+    lines = ["/// " + line for line in doc]
+    lines += ["#[rustfmt::skip]", "pub(super) static %s: &[(char, char)] = &[" % name]
+    entries = ["('\\u{%04X}', '\\u{%04X}')," % (first, last) for first, last in ranges(prop)]
+
+    for i in range(0, len(entries), RANGES_PER_LINE):
+        lines.append("    " + " ".join(entries[i : i + RANGES_PER_LINE]))
+
+    lines.append("];")
+    return "\n".join(lines)
+
+
+def main():
+    if len(sys.argv) != 2:
+        sys.exit("usage: xid_tables.py <unicode version>")
+
+    version = sys.argv[1]
+
+    print("// Generated by scripts/xid_tables.py from Unicode %s. Do not edit by hand" % version)
+    print()
+    print(table("XID_START", XID_START_DOC, "XID_Start"))
+    print()
+    print(table("XID_CONTINUE", XID_CONTINUE_DOC, "XID_Continue"))
+
+
+if __name__ == "__main__":
+    main()
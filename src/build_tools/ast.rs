@@ -1,35 +1,181 @@
 use crate::build_tools::token::*;
-use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
 
 /// Node - nodes in our ast will provide a token_literal and string methods for debugging
-trait Node {
+pub trait Node {
     fn token_literal(&self) -> String;
     fn string(&self) -> String;
+    fn span(&self) -> Span;
+}
+
+/// impl_display implements Display for nodes in terms of Node::string, so every node prints back
+/// to Monkey source with `{}`
+macro_rules! impl_display {
+    ($($node:ty),* $(,)?) => {
+        $(
+            impl fmt::Display for $node {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str(&self.string())
+                }
+            }
+        )*
+    };
+}
+
+impl_display!(
+    RootNode,
+    Statement,
+    Expression,
+    ErrorStatement,
+    Identifier,
+    IntegerLiteral,
+    FloatLiteral,
+    PrefixExpression,
+    Boolean,
+    IfExpression,
+    BlockStatement,
+    LetStatement,
+    ConstStatement,
+    ReturnStatement,
+    ExpressionStatement,
+    FunctionLiteral,
+    StringLiteral,
+    ArrayLiteral,
+    HashLiteral,
+    InfixExpression,
+    CallExpression,
+    IndexExpression,
+    PostfixExpression,
+);
+
+/// Statement - every kind of statement our parser produces. Statements do not produce values.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Statement {
+    Let(LetStatement),
+    Const(ConstStatement),
+    Return(ReturnStatement),
+    Expression(ExpressionStatement),
+    Error(ErrorStatement),
+}
+
+impl Statement {
+    /// node returns the wrapped statement as a Node, so the Node methods only need to be
+    /// dispatched in one place
+    fn node(&self) -> &dyn Node {
+        match self {
+            Statement::Let(stmt) => stmt,
+            Statement::Const(stmt) => stmt,
+            Statement::Return(stmt) => stmt,
+            Statement::Expression(stmt) => stmt,
+            Statement::Error(stmt) => stmt,
+        }
+    }
 }
 
-/// Statement - must provide statement_node, token_literal, and string methods. Statements do not produce values.
-pub trait Statement {
-    fn token_literal(&self) -> String;
-    fn string(&self) -> String;
-    fn statement_node(&self);
+impl Node for Statement {
+    /// token_literal returns the wrapped statement's literal and satisfies the Node interface.
+    fn token_literal(&self) -> String {
+        self.node().token_literal()
+    }
+    /// string - returns a string representation of the wrapped statement and satisfies our Node interface
+    fn string(&self) -> String {
+        self.node().string()
+    }
+    /// span returns the region of source the statement was parsed from
+    fn span(&self) -> Span {
+        self.node().span()
+    }
+}
+
+/// Expression - every kind of expression our parser produces. Expressions produce values.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Identifier(Identifier),
+    Integer(IntegerLiteral),
+    Float(FloatLiteral),
+    Boolean(Boolean),
+    String(StringLiteral),
+    Prefix(PrefixExpression),
+    Infix(InfixExpression),
+    Postfix(PostfixExpression),
+    If(IfExpression),
+    Function(FunctionLiteral),
+    Call(CallExpression),
+    Array(ArrayLiteral),
+    Hash(HashLiteral),
+    Index(IndexExpression),
+}
+
+impl Expression {
+    /// node returns the wrapped expression as a Node, so the Node methods only need to be
+    /// dispatched in one place
+    fn node(&self) -> &dyn Node {
+        match self {
+            Expression::Identifier(expr) => expr,
+            Expression::Integer(expr) => expr,
+            Expression::Float(expr) => expr,
+            Expression::Boolean(expr) => expr,
+            Expression::String(expr) => expr,
+            Expression::Prefix(expr) => expr,
+            Expression::Infix(expr) => expr,
+            Expression::Postfix(expr) => expr,
+            Expression::If(expr) => expr,
+            Expression::Function(expr) => expr,
+            Expression::Call(expr) => expr,
+            Expression::Array(expr) => expr,
+            Expression::Hash(expr) => expr,
+            Expression::Index(expr) => expr,
+        }
+    }
+
+    /// set_span replaces the region of source the wrapped expression covers. The parser uses it
+    /// to widen a parenthesized expression to include its parentheses
+    pub fn set_span(&mut self, span: Span) {
+        match self {
+            Expression::Identifier(expr) => expr.span = span,
+            Expression::Integer(expr) => expr.span = span,
+            Expression::Float(expr) => expr.span = span,
+            Expression::Boolean(expr) => expr.span = span,
+            Expression::String(expr) => expr.span = span,
+            Expression::Prefix(expr) => expr.span = span,
+            Expression::Infix(expr) => expr.span = span,
+            Expression::Postfix(expr) => expr.span = span,
+            Expression::If(expr) => expr.span = span,
+            Expression::Function(expr) => expr.span = span,
+            Expression::Call(expr) => expr.span = span,
+            Expression::Array(expr) => expr.span = span,
+            Expression::Hash(expr) => expr.span = span,
+            Expression::Index(expr) => expr.span = span,
+        }
+    }
 }
 
-/// Expression - must provide expression_node, token_literal, and string methods. Expressions produce values.
-pub trait Expression {
-    fn token_literal(&self) -> String;
-    fn string(&self) -> String;
-    fn expression_node(&self);
+impl Node for Expression {
+    /// token_literal returns the wrapped expression's literal and satisfies the Node interface.
+    fn token_literal(&self) -> String {
+        self.node().token_literal()
+    }
+    /// string - returns a string representation of the wrapped expression and satisfies our Node interface
+    fn string(&self) -> String {
+        self.node().string()
+    }
+    /// span returns the region of source the expression was parsed from
+    fn span(&self) -> Span {
+        self.node().span()
+    }
 }
 
 /// RootNode of every AST our parser produces.
-struct RootNode {
-    statements: Vec<Box<dyn Statement>>,
+#[derive(Clone, Debug, PartialEq)]
+pub struct RootNode {
+    pub statements: Vec<Statement>,
 }
 
-impl RootNode {
+impl Node for RootNode {
     /// token_literal returns the RootNode's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
-        if self.statements.len() > 0 {
+        if !self.statements.is_empty() {
             return self.statements[0].token_literal();
         }
         "".to_owned()
@@ -44,39 +190,46 @@ impl RootNode {
 
         statements_string
     }
+    /// span returns the region from the start of the first statement to the end of the last one
+    fn span(&self) -> Span {
+        match (self.statements.first(), self.statements.last()) {
+            (Some(first), Some(last)) => first.span().to(last.span()),
+            _ => Span::default(),
+        }
+    }
 }
 
-/// ZeroValueExpression - is used for initializations
-pub struct ZeroValueExpression {}
-impl Expression for ZeroValueExpression {
-    fn token_literal(&self) -> String {
-        "zero value".to_owned()
-    }
-    fn string(&self) -> String {
-        "zero value".to_owned()
-    }
-    fn expression_node(&self) {}
+/// ErrorStatement stands in for a statement that failed to parse. It holds the statement's first
+/// token and spans everything the parser skipped while recovering from the error
+#[derive(Clone, Debug, PartialEq)]
+pub struct ErrorStatement {
+    pub token: Token,
+    pub span: Span,
 }
 
-/// ZeroValueStatement is used for initializations
-pub struct ZeroValueStatement {}
-impl Statement for ZeroValueStatement {
+impl Node for ErrorStatement {
+    /// token_literal returns the ErrorStatement's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
-        "zero value".to_owned()
+        self.token.literal.clone()
     }
+    /// string - returns a string representation of the ErrorStatement and satisfies our Node interface
     fn string(&self) -> String {
-        "zero value".to_owned()
+        "<error>".to_owned()
+    }
+    fn span(&self) -> Span {
+        self.span
     }
-    fn statement_node(&self) {}
 }
 
 /// Identifier - holds IDENTIFIER token and it's value (add, foobar, x, y, ...)
+#[derive(Clone, Debug, PartialEq)]
 pub struct Identifier {
     pub token: Token,
+    pub span: Span,
     pub value: String,
 }
 
-impl Expression for Identifier {
+impl Node for Identifier {
     /// token_literal returns the Identifier's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
         self.token.literal.clone()
@@ -85,16 +238,20 @@ impl Expression for Identifier {
     fn string(&self) -> String {
         self.value.clone()
     }
-    fn expression_node(&self) {}
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
 /// IntegerLiteral - holds the token and it's value (int64)
+#[derive(Clone, Debug, PartialEq)]
 pub struct IntegerLiteral {
     pub token: Token,
-    pub value: usize,
+    pub span: Span,
+    pub value: i64,
 }
 
-impl Expression for IntegerLiteral {
+impl Node for IntegerLiteral {
     /// token_literal returns the IntegerLiteral's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
         self.token.literal.clone()
@@ -103,20 +260,46 @@ impl Expression for IntegerLiteral {
     fn string(&self) -> String {
         self.token.literal.clone()
     }
-    fn expression_node(&self) {}
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// FloatLiteral - holds the token and it's value (float64)
+#[derive(Clone, Debug, PartialEq)]
+pub struct FloatLiteral {
+    pub token: Token,
+    pub span: Span,
+    pub value: f64,
+}
+
+impl Node for FloatLiteral {
+    /// token_literal returns the FloatLiteral's literal and satisfies the Node interface.
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+    /// string - returns a string representation of the FloatLiteral and satisfies our Node interface
+    fn string(&self) -> String {
+        self.token.literal.clone()
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
 /// PrefixExpression - holds the token, a string version of the operator, and the expression to the right of it
+#[derive(Clone, Debug, PartialEq)]
 pub struct PrefixExpression {
     /// The prefix token (! or -)
     pub token: Token,
+    pub span: Span,
     /// String (either "!" or "-")
     pub operator: String,
     /// The expression to the right of the operator
-    pub right: Box<dyn Expression>,
+    pub right: Box<Expression>,
 }
 
-impl Expression for PrefixExpression {
+impl Node for PrefixExpression {
     /// token_literal returns the PrefixExpression's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
         self.token.literal.clone()
@@ -129,18 +312,22 @@ impl Expression for PrefixExpression {
         buf += ")";
         buf
     }
-    fn expression_node(&self) {}
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
 /// Boolean - holds the token and it's value (a boolean)
+#[derive(Clone, Debug, PartialEq)]
 pub struct Boolean {
     /// The prefix token (! or -)
     pub token: Token,
+    pub span: Span,
     /// String (either "!" or "-")
     pub value: bool,
 }
 
-impl Expression for Boolean {
+impl Node for Boolean {
     /// token_literal returns the Boolean's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
         self.token.literal.clone()
@@ -149,249 +336,414 @@ impl Expression for Boolean {
     fn string(&self) -> String {
         self.token.literal.clone()
     }
-    fn expression_node(&self) {}
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
 /// IfExpression - holds the token, the condition expression and the consequence & alternative
 /// block statements. Structure: if (<condition>) <consequence> else <alternative>
+#[derive(Clone, Debug, PartialEq)]
 pub struct IfExpression {
     pub token: Token, // The If token
-    pub condition: Box<dyn Expression>,
+    pub span: Span,
+    pub condition: Box<Expression>,
     pub consequence: BlockStatement,
-    pub alternative: BlockStatement,
+    pub alternative: Option<BlockStatement>,
 }
 
-impl Expression for IfExpression {
+impl Node for IfExpression {
     /// token_literal returns the IfExpression's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
     /// string - returns a string representation of the IfExpression and satisfies our Node interface
     fn string(&self) -> String {
-        let mut buf = "if".to_owned();
-        // TODO: match on condition and get it's Expression
-        // buf += self.condition.string();
-        buf += " ";
-        // buf += self.consequence.string();
-        // TODO: something besides nil
-        // if self.alternative != nil {
-        //     buf += " else ";
-        //     buf += self.alternative.string();
-        // }
+        let mut buf = format!(
+            "if ({}) {}",
+            self.condition.string(),
+            self.consequence.string()
+        );
+
+        if let Some(alternative) = &self.alternative {
+            buf += " else ";
+            buf += &alternative.string();
+        }
+
         buf
     }
 
-    fn expression_node(&self) {}
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
 /// BlockStatement - holds the token "{", and a slice of statements
+#[derive(Clone, Debug, PartialEq)]
 pub struct BlockStatement {
     pub token: Token,
-    pub statements: Vec<Box<dyn Statement>>,
+    pub span: Span,
+    pub statements: Vec<Statement>,
 }
 
-impl Statement for BlockStatement {
+impl Node for BlockStatement {
     /// token_literal returns the BlockStatement's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
     /// string - returns a string representation of the BlockStatement and satisfies our Node interface
     fn string(&self) -> String {
-        // TODO: loop over self.statements and call .string() on each
-        "BlockStatement".to_owned()
-    }
-    fn statement_node(&self) {}
-}
+        if self.statements.is_empty() {
+            return "{ }".to_owned();
+        }
 
-impl Expression for BlockStatement {
-    /// token_literal returns the BlockStatement's literal and satisfies the Node interface.
-    fn token_literal(&self) -> String {
-        self.token.literal.clone()
+        let statements: Vec<String> = self.statements.iter().map(|s| s.string()).collect();
+        format!("{{ {} }}", statements.join(" "))
     }
-    /// string - returns a string representation of the BlockStatement and satisfies our Node interface
-    fn string(&self) -> String {
-        // TODO: loop over self.statements and call .string() on each
-        "BlockStatement".to_owned()
+    fn span(&self) -> Span {
+        self.span
     }
-    fn expression_node(&self) {}
 }
 
 /// LetStatement - Name holds the identifier of the binding and Value for the expression that produces the value.
+#[derive(Clone, Debug, PartialEq)]
 pub struct LetStatement {
     pub token: Token,
+    pub span: Span,
     pub name: Identifier,
-    pub value: Box<dyn Expression>,
+    pub value: Expression,
+    /// The `///` doc comment lines right before the statement, joined with newlines
+    pub doc: Option<String>,
 }
 
-impl Statement for LetStatement {
+impl Node for LetStatement {
     /// token_literal returns the LetStatement's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
     /// string - returns a string representation of the LetStatement and satisfies our Node interface
     fn string(&self) -> String {
-        // TODO: actually implement this
-        "LetStatement".to_owned()
+        format!(
+            "{}let {} = {};",
+            doc_lines(&self.doc),
+            self.name.string(),
+            self.value.string()
+        )
+    }
+    fn span(&self) -> Span {
+        self.span
     }
-    fn statement_node(&self) {}
 }
 
 /// ConstStatement - Name holds the identifier of the binding and value for the expression that produces the value.
+#[derive(Clone, Debug, PartialEq)]
 pub struct ConstStatement {
     pub token: Token,
+    pub span: Span,
     pub name: Identifier,
-    pub value: Box<dyn Expression>,
+    pub value: Expression,
+    /// The `///` doc comment lines right before the statement, joined with newlines
+    pub doc: Option<String>,
 }
 
-impl Statement for ConstStatement {
+impl Node for ConstStatement {
     /// token_literal returns the ConstStatement's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
     /// string - returns a string representation of the ConstStatement and satisfies our Node interface
     fn string(&self) -> String {
-        // TODO: actually implement this
-        "ConstStatement".to_owned()
+        format!(
+            "{}const {} = {};",
+            doc_lines(&self.doc),
+            self.name.string(),
+            self.value.string()
+        )
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// doc_lines writes a binding's doc comment back out as `///` lines
+fn doc_lines(doc: &Option<String>) -> String {
+    match doc {
+        Some(doc) => doc
+            .split('\n')
+            .map(|line| match line {
+                "" => "///\n".to_owned(),
+                line => format!("/// {}\n", line),
+            })
+            .collect(),
+        None => String::new(),
     }
-    fn statement_node(&self) {}
 }
 
 /// ReturnStatement - pretty self explanatory, holds RETURN token and return value
+#[derive(Clone, Debug, PartialEq)]
 pub struct ReturnStatement {
     pub token: Token,
+    pub span: Span,
     /// The 'return' token
-    pub return_value: Box<dyn Expression>,
+    pub return_value: Expression,
 }
 
-impl Statement for ReturnStatement {
+impl Node for ReturnStatement {
     /// token_literal returns the ReturnStatement's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
     /// string - returns a string representation of the ReturnStatement and satisfies our Node interface
     fn string(&self) -> String {
-        // TODO: actually implement this
-        "ReturnStatement".to_owned()
+        format!("return {};", self.return_value.string())
+    }
+    fn span(&self) -> Span {
+        self.span
     }
-    fn statement_node(&self) {}
 }
 
 /// ExpressionStatement - holds the first token of the expression and the expression
+#[derive(Clone, Debug, PartialEq)]
 pub struct ExpressionStatement {
     pub token: Token,
+    pub span: Span,
     /// The first token of the expression
-    pub expression: Box<dyn Expression>,
+    pub expression: Expression,
 }
 
-impl Statement for ExpressionStatement {
+impl Node for ExpressionStatement {
     /// token_literal returns the ExpressionStatement's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
     /// string - returns a string representation of the ExpressionStatement and satisfies our Node interface
     fn string(&self) -> String {
-        // TODO: actually implement this
-        "ExpressionStatement".to_owned()
+        format!("{};", self.expression.string())
+    }
+    fn span(&self) -> Span {
+        self.span
     }
-    fn statement_node(&self) {}
 }
 
 // FunctionLiteral - holds the token, the function params (a vec of Identifier), and
 // the function Body (BlockStatement). Structure: func <parameters> <block statement>
+#[derive(Clone, Debug, PartialEq)]
 pub struct FunctionLiteral {
     pub token: Token, // The 'func' token
+    pub span: Span,
     pub parameters: Vec<Identifier>,
-    pub body: BlockStatement,
-    pub name: String
+    pub body: Rc<BlockStatement>,
+    pub name: String,
 }
 
-impl Expression for FunctionLiteral {
+impl Node for FunctionLiteral {
     /// token_literal returns the FunctionLiteral's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
     /// string - returns a string representation of the FunctionLiteral and satisfies our Node interface
     fn string(&self) -> String {
-        // TODO: actually implement them
-        "FunctionLiteral".to_owned()
+        let parameters: Vec<String> = self.parameters.iter().map(|p| p.string()).collect();
+        format!("func({}) {}", parameters.join(", "), self.body.string())
+    }
+    fn span(&self) -> Span {
+        self.span
     }
-    fn expression_node(&self) {}
 }
 
 /// StringLiteral holds the token and it's value (string)
+#[derive(Clone, Debug, PartialEq)]
 pub struct StringLiteral {
     pub token: Token,
+    pub span: Span,
     pub value: String,
 }
 
-impl Expression for StringLiteral {
+impl Node for StringLiteral {
     /// token_literal returns the StringLiteral's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
     /// string - returns a string representation of the StringLiteral and satisfies our Node interface
     fn string(&self) -> String {
-        self.token.literal.clone()
+        let mut out = String::from("\"");
+
+        // Escape the value again, so the output lexes back to the same string
+        for ch in self.value.chars() {
+            match ch {
+                '"' => out += "\\\"",
+                '\\' => out += "\\\\",
+                '\n' => out += "\\n",
+                '\t' => out += "\\t",
+                '\r' => out += "\\r",
+                '\0' => out += "\\0",
+                ch if ch.is_control() => out += &format!("\\u{{{:x}}}", ch as u32),
+                ch => out.push(ch),
+            }
+        }
+
+        out.push('"');
+        out
+    }
+    fn span(&self) -> Span {
+        self.span
     }
-    fn expression_node(&self) {}
 }
 
 /// ArrayLiteral holds the token: '[' and an array of expressions (Elements)
+#[derive(Clone, Debug, PartialEq)]
 pub struct ArrayLiteral {
-    pub token: Token,/// the '[' token
-    pub elements: Vec<Box<dyn Expression>>,
+    pub token: Token,
+    /// the '[' token
+    pub span: Span,
+    pub elements: Vec<Expression>,
 }
 
-impl Expression for ArrayLiteral {
+impl Node for ArrayLiteral {
     /// token_literal returns the ArrayLiteral's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
     /// string - returns a string representation of the ArrayLiteral and satisfies our Node interface
     fn string(&self) -> String {
-        // TODO: actually implement them
-        "ArrayLiteral".to_owned()
+        let elements: Vec<String> = self.elements.iter().map(|e| e.string()).collect();
+        format!("[{}]", elements.join(", "))
+    }
+    fn span(&self) -> Span {
+        self.span
     }
-    fn expression_node(&self) {}
 }
 
-/// HashLiteral holds the '{' token and the pairs in the hash
+/// HashLiteral holds the '{' token and the (key, value) pairs in the hash, in source order
+#[derive(Clone, Debug, PartialEq)]
 pub struct HashLiteral {
-    pub token: Token, /// The '{' token
-    pub pairs: HashMap<String, Box<dyn Expression>>
+    pub token: Token,
+    /// The '{' token
+    pub span: Span,
+    pub pairs: Vec<(Expression, Expression)>,
 }
 
-impl Expression for HashLiteral {
+impl Node for HashLiteral {
     /// token_literal returns the HashLiteral's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
     /// string - returns a string representation of the HashLiteral and satisfies our Node interface
     fn string(&self) -> String {
-        // TODO: actually implement them
-        "HashLiteral".to_owned()
+        let pairs: Vec<String> = self
+            .pairs
+            .iter()
+            .map(|(key, value)| format!("{}: {}", key.string(), value.string()))
+            .collect();
+        format!("{{{}}}", pairs.join(", "))
+    }
+    fn span(&self) -> Span {
+        self.span
     }
-    fn expression_node(&self) {}
 }
 
 /// InfixExpression holds the token, the expression to the left of it, a string version of
 // the operator, and the expression to the right of it
+#[derive(Clone, Debug, PartialEq)]
 pub struct InfixExpression {
-    pub token: Token, /// The operator token (+, -, *, etc)
-    pub left: Box<dyn Expression>,
+    pub token: Token,
+    /// The operator token (+, -, *, etc)
+    pub span: Span,
+    pub left: Box<Expression>,
     pub operator: String,
-    pub right: Box<dyn Expression>,
+    pub right: Box<Expression>,
 }
 
-impl Expression for InfixExpression {
+impl Node for InfixExpression {
     /// token_literal returns the InfixExpression's literal and satisfies the Node interface.
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
     /// string - returns a string representation of the InfixExpression and satisfies our Node interface
     fn string(&self) -> String {
-        // TODO: actually implement them
-        "InfixExpression".to_owned()
+        format!(
+            "({} {} {})",
+            self.left.string(),
+            self.operator,
+            self.right.string()
+        )
     }
-    fn expression_node(&self) {}
-}
\ No newline at end of file
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+/// CallExpression holds the '(' token, the expression being called (an identifier or a function
+/// literal) and its arguments. Structure: <expression>(<comma separated expressions>)
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallExpression {
+    pub token: Token,
+    /// The '(' token
+    pub span: Span,
+    pub function: Box<Expression>,
+    pub arguments: Vec<Expression>,
+}
+
+impl Node for CallExpression {
+    /// token_literal returns the CallExpression's literal and satisfies the Node interface.
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+    /// string - returns a string representation of the CallExpression and satisfies our Node interface
+    fn string(&self) -> String {
+        let arguments: Vec<String> = self.arguments.iter().map(|a| a.string()).collect();
+        format!("{}({})", self.function.string(), arguments.join(", "))
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// IndexExpression holds the '[' token, the expression being indexed, and the index.
+/// Structure: <expression>[<expression>]
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexExpression {
+    pub token: Token,
+    /// The '[' token
+    pub span: Span,
+    pub left: Box<Expression>,
+    pub index: Box<Expression>,
+}
+
+impl Node for IndexExpression {
+    /// token_literal returns the IndexExpression's literal and satisfies the Node interface.
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+    /// string - returns a string representation of the IndexExpression and satisfies our Node interface
+    fn string(&self) -> String {
+        format!("({}[{}])", self.left.string(), self.index.string())
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// PostfixExpression holds the operator token, the identifier it applies to, and a string version
+/// of the operator. Structure: <identifier>++ or <identifier>--
+#[derive(Clone, Debug, PartialEq)]
+pub struct PostfixExpression {
+    pub token: Token,
+    /// The postfix operator token (++ or --)
+    pub span: Span,
+    pub left: Identifier,
+    pub operator: String,
+}
+
+impl Node for PostfixExpression {
+    /// token_literal returns the PostfixExpression's literal and satisfies the Node interface.
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+    /// string - returns a string representation of the PostfixExpression and satisfies our Node interface
+    fn string(&self) -> String {
+        format!("({}{})", self.left.string(), self.operator)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+}
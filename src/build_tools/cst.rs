@@ -0,0 +1,360 @@
+use crate::build_tools::ast::*;
+use crate::build_tools::error::ParseError;
+use crate::build_tools::lexer::Lexer;
+use crate::build_tools::parser::Parser;
+use crate::build_tools::token::{Span, TokenType};
+use crate::build_tools::visit::{self, Visitor};
+
+use std::fmt;
+use std::iter::Peekable;
+
+/// SyntaxKind - the kind of AST node a SyntaxNode was built from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    Program,
+    LetStatement,
+    ConstStatement,
+    ReturnStatement,
+    ExpressionStatement,
+    ErrorStatement,
+    BlockStatement,
+    Identifier,
+    IntegerLiteral,
+    FloatLiteral,
+    Boolean,
+    StringLiteral,
+    PrefixExpression,
+    InfixExpression,
+    PostfixExpression,
+    IfExpression,
+    FunctionLiteral,
+    CallExpression,
+    ArrayLiteral,
+    HashLiteral,
+    IndexExpression,
+}
+
+/// SyntaxNode - a node in the concrete syntax tree. It mirrors an AST node, but also holds every
+/// token inside its span that none of its child nodes do, trivia (whitespace and comments)
+/// included. Nothing in the source is left out, so a tree's text is exactly the source it was
+/// parsed from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub span: Span,
+    /// Child nodes and tokens, in source order
+    pub children: Vec<SyntaxElement>,
+}
+
+/// SyntaxElement - a child of a SyntaxNode
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// SyntaxToken - a token in the concrete syntax tree. text is the token exactly as it appears in
+/// the source, unlike Token.literal (e.g. a STRING's literal leaves out its quotes)
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxToken {
+    pub token_type: TokenType,
+    pub text: String,
+    pub span: Span,
+}
+
+impl SyntaxToken {
+    /// is_trivia reports whether the token is whitespace or a comment
+    pub fn is_trivia(&self) -> bool {
+        self.token_type == TokenType::WHITESPACE || self.token_type == TokenType::COMMENT
+    }
+}
+
+impl SyntaxNode {
+    /// text returns the source text of the node, trivia included
+    pub fn text(&self) -> String {
+        let mut buf = String::new();
+        self.write_text(&mut buf);
+        buf
+    }
+
+    fn write_text(&self, buf: &mut String) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.write_text(buf),
+                SyntaxElement::Token(token) => buf.push_str(&token.text),
+            }
+        }
+    }
+
+    /// nodes returns the node's direct child nodes
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// tokens returns every token in the node and its descendants, in source order
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = vec![];
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a SyntaxToken>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text())
+    }
+}
+
+/// parse parses `source` into a concrete syntax tree rooted at a Program node, along with any
+/// errors the parser hit. Statements that failed to parse become ErrorStatement nodes holding the
+/// tokens the parser skipped, so the tree is lossless even for broken input.
+pub fn parse(source: &str) -> (SyntaxNode, Vec<ParseError>) {
+    let mut parser = Parser::new(Lexer::new(source));
+    let program = parser.parse_program();
+
+    let mut builder = ShapeBuilder {
+        stack: vec![Shape {
+            kind: SyntaxKind::Program,
+            span: Span::new(0, source.len(), 1, 1),
+            children: vec![],
+        }],
+    };
+    builder.visit_program(&program);
+
+    let shape = builder
+        .stack
+        .pop()
+        .expect("the Program shape is never popped by a visit");
+    let mut tokens = Lexer::with_trivia(source).peekable();
+    let mut root = build(shape, &mut tokens, source);
+
+    // Only whitespace and comments can follow the last statement, but take everything left so
+    // the tree always covers the whole source
+    take_tokens_before(usize::MAX, &mut tokens, source, &mut root.children);
+
+    (root, parser.errors().to_vec())
+}
+
+/// Shape is the nesting of AST nodes and their spans, without any tokens
+struct Shape {
+    kind: SyntaxKind,
+    span: Span,
+    children: Vec<Shape>,
+}
+
+/// build lays the tokens over `shape`: each token goes to the innermost node whose span it is in
+fn build(shape: Shape, tokens: &mut Peekable<Lexer>, source: &str) -> SyntaxNode {
+    let mut children = vec![];
+
+    for child in shape.children {
+        take_tokens_before(child.span.start, tokens, source, &mut children);
+        children.push(SyntaxElement::Node(build(child, tokens, source)));
+    }
+
+    take_tokens_before(shape.span.end, tokens, source, &mut children);
+
+    SyntaxNode {
+        kind: shape.kind,
+        span: shape.span,
+        children,
+    }
+}
+
+fn take_tokens_before(
+    end: usize,
+    tokens: &mut Peekable<Lexer>,
+    source: &str,
+    children: &mut Vec<SyntaxElement>,
+) {
+    while let Some(token) = tokens.next_if(|token| token.span.start < end) {
+        children.push(SyntaxElement::Token(SyntaxToken {
+            token_type: token.token_type,
+            text: source[token.span.start..token.span.end].to_owned(),
+            span: token.span,
+        }));
+    }
+}
+
+/// ShapeBuilder visits an AST and records the Shape of every node in it
+struct ShapeBuilder {
+    /// The shapes of the nodes currently being visited, innermost last
+    stack: Vec<Shape>,
+}
+
+impl ShapeBuilder {
+    /// node records a node of `kind`, running `walk` to record its children inside it
+    fn node(&mut self, kind: SyntaxKind, span: Span, walk: impl FnOnce(&mut Self)) {
+        self.stack.push(Shape {
+            kind,
+            span,
+            children: vec![],
+        });
+
+        walk(self);
+
+        let shape = self.stack.pop().expect("node pushed the shape it pops");
+        self.stack
+            .last_mut()
+            .expect("the Program shape stays at the bottom of the stack")
+            .children
+            .push(shape);
+    }
+}
+
+impl<'ast> Visitor<'ast> for ShapeBuilder {
+    fn visit_let_statement(&mut self, stmt: &'ast LetStatement) {
+        self.node(SyntaxKind::LetStatement, stmt.span, |b| {
+            visit::walk_let_statement(b, stmt)
+        });
+    }
+    fn visit_const_statement(&mut self, stmt: &'ast ConstStatement) {
+        self.node(SyntaxKind::ConstStatement, stmt.span, |b| {
+            visit::walk_const_statement(b, stmt)
+        });
+    }
+    fn visit_return_statement(&mut self, stmt: &'ast ReturnStatement) {
+        self.node(SyntaxKind::ReturnStatement, stmt.span, |b| {
+            visit::walk_return_statement(b, stmt)
+        });
+    }
+    fn visit_expression_statement(&mut self, stmt: &'ast ExpressionStatement) {
+        self.node(SyntaxKind::ExpressionStatement, stmt.span, |b| {
+            visit::walk_expression_statement(b, stmt)
+        });
+    }
+    fn visit_error_statement(&mut self, stmt: &'ast ErrorStatement) {
+        self.node(SyntaxKind::ErrorStatement, stmt.span, |_| {});
+    }
+    fn visit_block_statement(&mut self, block: &'ast BlockStatement) {
+        self.node(SyntaxKind::BlockStatement, block.span, |b| {
+            visit::walk_block_statement(b, block)
+        });
+    }
+    fn visit_identifier(&mut self, ident: &'ast Identifier) {
+        self.node(SyntaxKind::Identifier, ident.span, |_| {});
+    }
+    fn visit_integer_literal(&mut self, lit: &'ast IntegerLiteral) {
+        self.node(SyntaxKind::IntegerLiteral, lit.span, |_| {});
+    }
+    fn visit_float_literal(&mut self, lit: &'ast FloatLiteral) {
+        self.node(SyntaxKind::FloatLiteral, lit.span, |_| {});
+    }
+    fn visit_boolean(&mut self, boolean: &'ast Boolean) {
+        self.node(SyntaxKind::Boolean, boolean.span, |_| {});
+    }
+    fn visit_string_literal(&mut self, lit: &'ast StringLiteral) {
+        self.node(SyntaxKind::StringLiteral, lit.span, |_| {});
+    }
+    fn visit_prefix_expression(&mut self, expr: &'ast PrefixExpression) {
+        self.node(SyntaxKind::PrefixExpression, expr.span, |b| {
+            visit::walk_prefix_expression(b, expr)
+        });
+    }
+    fn visit_infix_expression(&mut self, expr: &'ast InfixExpression) {
+        self.node(SyntaxKind::InfixExpression, expr.span, |b| {
+            visit::walk_infix_expression(b, expr)
+        });
+    }
+    fn visit_postfix_expression(&mut self, expr: &'ast PostfixExpression) {
+        self.node(SyntaxKind::PostfixExpression, expr.span, |b| {
+            visit::walk_postfix_expression(b, expr)
+        });
+    }
+    fn visit_if_expression(&mut self, expr: &'ast IfExpression) {
+        self.node(SyntaxKind::IfExpression, expr.span, |b| {
+            visit::walk_if_expression(b, expr)
+        });
+    }
+    fn visit_function_literal(&mut self, lit: &'ast FunctionLiteral) {
+        self.node(SyntaxKind::FunctionLiteral, lit.span, |b| {
+            visit::walk_function_literal(b, lit)
+        });
+    }
+    fn visit_call_expression(&mut self, expr: &'ast CallExpression) {
+        self.node(SyntaxKind::CallExpression, expr.span, |b| {
+            visit::walk_call_expression(b, expr)
+        });
+    }
+    fn visit_array_literal(&mut self, lit: &'ast ArrayLiteral) {
+        self.node(SyntaxKind::ArrayLiteral, lit.span, |b| {
+            visit::walk_array_literal(b, lit)
+        });
+    }
+    fn visit_hash_literal(&mut self, lit: &'ast HashLiteral) {
+        self.node(SyntaxKind::HashLiteral, lit.span, |b| {
+            visit::walk_hash_literal(b, lit)
+        });
+    }
+    fn visit_index_expression(&mut self, expr: &'ast IndexExpression) {
+        self.node(SyntaxKind::IndexExpression, expr.span, |b| {
+            visit::walk_index_expression(b, expr)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_text_is_the_source() {
+        let sources = [
+            "",
+            "  \n\t",
+            "let x = 1; // one\n/* two */ x",
+            "let add = func(a, b) {\r\n    a + b\r\n};\r\n",
+            "/* outer /* inner */ still outer */ (a + b) * c",
+            "let s = \"x\0y\"; let t = \0; t",
+            "let 名前 = \"é\\u{1F600}\"; 名前",
+            // Broken input keeps every byte too
+            "let x = ; let = 2; if (x { y",
+            "let s = \"unterminated",
+            "x /* unterminated",
+            "1 & 2 @ 0x 1__0",
+        ];
+
+        for source in sources.iter() {
+            let (tree, _) = parse(source);
+
+            assert_eq!(tree.text(), *source);
+            assert_eq!(tree.span, Span::new(0, source.len(), 1, 1));
+        }
+    }
+
+    #[test]
+    fn tree_keeps_trivia_as_tokens() {
+        let (tree, errors) = parse("let x = 1; // note\n");
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let trivia: Vec<(TokenType, String)> = tree
+            .tokens()
+            .into_iter()
+            .filter(|token| token.is_trivia())
+            .map(|token| (token.token_type, token.text.clone()))
+            .collect();
+
+        assert_eq!(
+            trivia,
+            [
+                (TokenType::WHITESPACE, " ".to_owned()),
+                (TokenType::WHITESPACE, " ".to_owned()),
+                (TokenType::WHITESPACE, " ".to_owned()),
+                (TokenType::WHITESPACE, " ".to_owned()),
+                (TokenType::COMMENT, "// note".to_owned()),
+                (TokenType::WHITESPACE, "\n".to_owned()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,475 @@
+use crate::build_tools::error::{radix_name, LexError, ParseError};
+use crate::build_tools::token::{Span, TokenType};
+use crate::build_tools::unicode;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Label - a message attached to a span of source
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// Diagnostic describes a problem in a Monkey program: a headline message, a primary label on
+/// the offending source, optional secondary labels on related source, and trailing notes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub primary: Label,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(message: String, span: Span, label: String) -> Diagnostic {
+        Diagnostic {
+            message,
+            primary: Label {
+                span,
+                message: label,
+            },
+            secondary: vec![],
+            notes: vec![],
+        }
+    }
+
+    /// with_secondary adds a label pointing at related source
+    pub fn with_secondary(mut self, span: Span, message: String) -> Diagnostic {
+        self.secondary.push(Label { span, message });
+        self
+    }
+
+    /// with_note adds a note printed after the source snippet
+    pub fn with_note(mut self, note: String) -> Diagnostic {
+        self.notes.push(note);
+        self
+    }
+
+    /// render formats the diagnostic rustc style: the message, the location, then each source
+    /// line a label touches with the labelled region underlined (^ for the primary label, - for
+    /// secondary ones), followed by any notes. `color` turns on ANSI escape codes.
+    pub fn render(&self, filename: &str, source: &str, color: bool) -> String {
+        let paint = |style: &str, text: &str| {
+            if color {
+                format!("{}{}{}", style, text, RESET)
+            } else {
+                text.to_owned()
+            }
+        };
+
+        let lines = line_starts(source);
+        let mut labels: Vec<(&Label, bool)> = vec![(&self.primary, true)];
+        labels.extend(self.secondary.iter().map(|label| (label, false)));
+
+        let (primary_line, primary_column) = locate(source, &lines, self.primary.span.start);
+        let last_line = labels
+            .iter()
+            .map(|(label, _)| locate(source, &lines, label.span.end).0)
+            .max()
+            .unwrap_or(primary_line);
+        let width = last_line.to_string().len();
+        let gutter = " ".repeat(width);
+
+        let mut out = format!(
+            "{}{}\n",
+            paint(RED, "error"),
+            paint(BOLD, &format!(": {}", self.message))
+        );
+        out += &format!(
+            "{}{} {}:{}:{}\n",
+            gutter,
+            paint(BLUE, "-->"),
+            filename,
+            primary_line,
+            primary_column
+        );
+        out += &format!("{} {}\n", gutter, paint(BLUE, "|"));
+
+        // Every line touched by any label, in order, each printed once
+        let mut touched: Vec<usize> = vec![];
+        for (label, _) in &labels {
+            let first = locate(source, &lines, label.span.start).0;
+            let last = locate(source, &lines, label.span.end.max(label.span.start + 1) - 1).0;
+            touched.extend(first..=last.max(first));
+        }
+        touched.sort_unstable();
+        touched.dedup();
+
+        let mut previous: Option<usize> = None;
+        for line in touched {
+            if let Some(previous) = previous {
+                if line > previous + 1 {
+                    out += &format!("{}\n", paint(BLUE, "..."));
+                }
+            }
+            previous = Some(line);
+
+            let text = line_text(source, &lines, line);
+            out += &format!(
+                "{}{}\n",
+                paint(BLUE, &format!("{:>width$} | ", line, width = width)),
+                text
+            );
+
+            for (label, is_primary) in &labels {
+                if let Some(underline) = underline(source, &lines, line, label, *is_primary) {
+                    let style = if *is_primary { RED } else { BLUE };
+                    out += &format!(
+                        "{} {} {}\n",
+                        gutter,
+                        paint(BLUE, "|"),
+                        paint(style, &underline)
+                    );
+                }
+            }
+        }
+
+        if !self.notes.is_empty() {
+            out += &format!("{} {}\n", gutter, paint(BLUE, "|"));
+        }
+        for note in &self.notes {
+            out += &format!(
+                "{} {} {}\n",
+                gutter,
+                paint(BLUE, "="),
+                paint(BOLD, "note:") + " " + note
+            );
+        }
+
+        out
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Diagnostic {
+        let message = error.to_string();
+
+        match error {
+            ParseError::UnexpectedToken { expected, .. } => {
+                Diagnostic::new(message, error.span(), format!("expected {}", expected))
+            }
+            ParseError::MissingPrefixFunction { .. } => {
+                Diagnostic::new(message, error.span(), "expected an expression".to_owned())
+            }
+            ParseError::Unterminated {
+                expected, opened, ..
+            } => {
+                let diagnostic =
+                    Diagnostic::new(message, error.span(), format!("expected {}", expected));
+                match opened {
+                    Some(opened) => {
+                        diagnostic.with_secondary(*opened, "unclosed delimiter".to_owned())
+                    }
+                    None => diagnostic,
+                }
+            }
+            ParseError::InvalidLiteral { token_type, .. } => {
+                let diagnostic =
+                    Diagnostic::new(message, error.span(), "invalid literal".to_owned());
+                match token_type {
+                    TokenType::INTEGER => diagnostic
+                        .with_note(format!("integer literals must be at most {}", i64::MAX)),
+                    TokenType::FLOAT => diagnostic
+                        .with_note(format!("float literals must be at most {:e}", f64::MAX)),
+                    _ => diagnostic,
+                }
+            }
+            ParseError::NestingTooDeep { .. } => {
+                Diagnostic::new(message, error.span(), "nested too deeply".to_owned())
+                    .with_note("split the expression up using let bindings".to_owned())
+            }
+            ParseError::Lex(error) => Diagnostic::from(error),
+        }
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(error: &LexError) -> Diagnostic {
+        let message = error.to_string();
+
+        match error {
+            LexError::UnterminatedString { .. } => {
+                Diagnostic::new(message, error.span(), "string starts here".to_owned())
+                    .with_note("add a closing `\"`".to_owned())
+            }
+            LexError::UnknownEscape { .. } => {
+                Diagnostic::new(message, error.span(), "unknown escape".to_owned()).with_note(
+                    r#"valid escapes are \n, \t, \r, \0, \\, \" and \u{...}"#.to_owned(),
+                )
+            }
+            LexError::InvalidUnicodeEscape { .. } => {
+                Diagnostic::new(message, error.span(), "invalid unicode escape".to_owned())
+                    .with_note(
+                        r"unicode escapes are \u{...} around 1 to 6 hex digits naming a Unicode scalar value"
+                            .to_owned(),
+                    )
+            }
+            LexError::MissingDigits { prefix, .. } => {
+                Diagnostic::new(message, error.span(), "no digits".to_owned()).with_note(format!(
+                    "write at least one digit after `{}`, e.g. `{}1`",
+                    prefix, prefix
+                ))
+            }
+            LexError::InvalidDigit { radix, .. } => Diagnostic::new(
+                message,
+                error.span(),
+                format!("not a valid {} digit", radix_name(*radix)),
+            )
+            .with_note(format!(
+                "{} literals use the digits {}",
+                radix_name(*radix),
+                match radix {
+                    2 => "0 and 1",
+                    8 => "0 to 7",
+                    16 => "0 to 9 and a to f, in either case",
+                    _ => "0 to 9",
+                }
+            )),
+            LexError::MisplacedSeparator { .. } => {
+                Diagnostic::new(message, error.span(), "misplaced `_`".to_owned())
+                    .with_note("a `_` in a number must be between two digits".to_owned())
+            }
+            LexError::UnterminatedComment { .. } => {
+                Diagnostic::new(message, error.span(), "comment starts here".to_owned())
+                    .with_note(
+                        "block comments nest, so every `/*` needs a matching `*/`".to_owned(),
+                    )
+            }
+            LexError::UnexpectedCharacter { character: '\'', .. } => {
+                Diagnostic::new(message, error.span(), "unexpected character".to_owned())
+                    .with_note("strings are written with double quotes, e.g. \"text\"".to_owned())
+            }
+            LexError::UnexpectedCharacter { .. } => {
+                Diagnostic::new(message, error.span(), "unexpected character".to_owned())
+            }
+            LexError::LoneOperator { operator, .. } => Diagnostic::new(
+                message,
+                error.span(),
+                format!("did you mean `{}{}`?", operator, operator),
+            )
+            .with_note(format!(
+                "Monkey has no bitwise operators, `{}{}` is logical {}",
+                operator,
+                operator,
+                if *operator == '&' { "and" } else { "or" }
+            )),
+        }
+    }
+}
+
+/// line_starts returns the byte offset at which each line of source begins
+fn line_starts(source: &str) -> Vec<usize> {
+    let mut starts = vec![0];
+    starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+    starts
+}
+
+/// locate converts a byte offset into a 1-based (line, column) pair, counting columns in characters.
+/// An offset inside a multi-byte character, such as the last byte of a span ending in one, is
+/// located at the start of that character
+fn locate(source: &str, lines: &[usize], offset: usize) -> (usize, usize) {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let line = match lines.binary_search(&offset) {
+        Ok(index) => index,
+        Err(index) => index - 1,
+    };
+    let column = source[lines[line]..offset].chars().count() + 1;
+
+    (line + 1, column)
+}
+
+/// line_text returns the text of a 1-based line, without its line terminator
+fn line_text<'a>(source: &'a str, lines: &[usize], line: usize) -> &'a str {
+    let start = lines[line - 1];
+    let end = lines.get(line).map(|next| next - 1).unwrap_or(source.len());
+
+    source[start..end].trim_end_matches('\r')
+}
+
+/// underline returns the marker row for `label` under `line`, or None if the label doesn't touch
+/// that line. The label's message is printed after the markers on the last line it covers.
+fn underline(
+    source: &str,
+    lines: &[usize],
+    line: usize,
+    label: &Label,
+    is_primary: bool,
+) -> Option<String> {
+    let (start_line, _) = locate(source, lines, label.span.start);
+    let last_offset = label.span.end.max(label.span.start + 1) - 1;
+    let (end_line, _) = locate(source, lines, last_offset);
+
+    if line < start_line || line > end_line.max(start_line) {
+        return None;
+    }
+
+    let text = line_text(source, lines, line);
+    let line_start = lines[line - 1];
+    let from = if line == start_line {
+        label.span.start - line_start
+    } else {
+        0
+    };
+    let to = if line == end_line {
+        (label.span.end.max(label.span.start) - line_start).min(text.len())
+    } else {
+        text.len()
+    };
+    let from = from.min(text.len());
+
+    // Mirror tabs from the source line, and pad by each character's width on screen, so the
+    // markers stay aligned under wide characters such as CJK
+    let padding: String = text[..from]
+        .chars()
+        .map(|c| match c {
+            '\t' => "\t".to_owned(),
+            c => " ".repeat(unicode::display_width(c)),
+        })
+        .collect();
+    let marker = if is_primary { "^" } else { "-" };
+    let width: usize = text[from..to.max(from)]
+        .chars()
+        .map(unicode::display_width)
+        .sum();
+    let markers = marker.repeat(width.max(1));
+
+    let mut row = padding + &markers;
+    if line == end_line.max(start_line) && !label.message.is_empty() {
+        row += " ";
+        row += &label.message;
+    }
+
+    Some(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_tools::lexer::Lexer;
+    use crate::build_tools::parser::Parser;
+
+    /// first_error parses `source` and returns its first error as a Diagnostic
+    fn first_error(source: &str) -> Diagnostic {
+        let mut parser = Parser::new(Lexer::new(source));
+        parser.parse_program();
+
+        Diagnostic::from(&parser.errors()[0])
+    }
+
+    #[test]
+    fn render_single_line() {
+        let source = "let x = ;";
+
+        assert_eq!(
+            first_error(source).render("test.mk", source, false),
+            "\
+error: expected an expression, found SEMICOLON `;`
+ --> test.mk:1:9
+  |
+1 | let x = ;
+  |         ^ expected an expression
+"
+        );
+    }
+
+    #[test]
+    fn render_secondary_label_and_skipped_lines() {
+        let source = "if (x) {\n  let a = 1;\n  let b = 2;\n  a";
+
+        assert_eq!(
+            first_error(source).render("test.mk", source, false),
+            "\
+error: unexpected end of input, expected RIGHT_BRACE
+ --> test.mk:4:4
+  |
+1 | if (x) {
+  |        - unclosed delimiter
+...
+4 |   a
+  |    ^ expected RIGHT_BRACE
+"
+        );
+    }
+
+    #[test]
+    fn render_multi_line_label() {
+        let source = "let s = \"ab\ncd\";";
+        let diagnostic = Diagnostic::new(
+            "multi-line".to_owned(),
+            Span::new(8, 15, 1, 9),
+            "the string".to_owned(),
+        )
+        .with_note("a note".to_owned());
+
+        assert_eq!(
+            diagnostic.render("test.mk", source, false),
+            "\
+error: multi-line
+ --> test.mk:1:9
+  |
+1 | let s = \"ab
+  |         ^^^
+2 | cd\";
+  | ^^^ the string
+  |
+  = note: a note
+"
+        );
+    }
+
+    #[test]
+    fn render_aligns_under_tabs_and_wide_characters() {
+        let source = "\tlet 名前 = @;";
+
+        assert_eq!(
+            first_error(source).render("test.mk", source, false),
+            "\
+error: unexpected character `@`
+ --> test.mk:1:11
+  |
+1 | \tlet 名前 = @;
+  | \t           ^ unexpected character
+"
+        );
+
+        // Wide characters take two markers each
+        let diagnostic =
+            Diagnostic::new("name".to_owned(), Span::new(5, 11, 1, 6), "here".to_owned());
+        let rendered = diagnostic.render("test.mk", source, false);
+        assert!(rendered.ends_with("  | \t    ^^^^ here\n"), "{}", rendered);
+    }
+
+    #[test]
+    fn render_label_ending_in_a_multi_byte_character() {
+        let source = "let x = €;";
+
+        assert_eq!(
+            first_error(source).render("test.mk", source, false),
+            "\
+error: unexpected character `€`
+ --> test.mk:1:9
+  |
+1 | let x = €;
+  |         ^ unexpected character
+"
+        );
+    }
+
+    #[test]
+    fn render_color() {
+        let source = "let x = ;";
+        let diagnostic = first_error(source);
+
+        assert!(!diagnostic.render("test.mk", source, false).contains('\x1b'));
+        assert!(diagnostic
+            .render("test.mk", source, true)
+            .starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: expected an expression"));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::build_tools::ast::*;
+use crate::build_tools::token::Span;
+use crate::build_tools::visit::{self, Visitor};
+
+/// dump returns an indented outline of the AST, one node per line with the line:column it starts
+/// at, e.g.
+///
+/// ```text
+/// LetStatement 1:1
+///   Identifier x 1:5
+///   InfixExpression + 1:9
+///     IntegerLiteral 1 1:9
+///     IntegerLiteral 2 1:13
+/// ```
+pub fn dump(program: &RootNode) -> String {
+    let mut dumper = Dumper {
+        depth: 0,
+        out: String::new(),
+    };
+
+    dumper.visit_program(program);
+    dumper.out
+}
+
+struct Dumper {
+    depth: usize,
+    out: String,
+}
+
+impl Dumper {
+    /// line writes a line for a node named `kind`, with an optional `detail` such as its operator
+    /// or value, then runs `walk` to write its children one level deeper
+    fn line(&mut self, kind: &str, detail: &str, span: Span, walk: impl FnOnce(&mut Self)) {
+        for _ in 0..self.depth {
+            self.out += "  ";
+        }
+
+        self.out += kind;
+        if !detail.is_empty() {
+            self.out.push(' ');
+            self.out += detail;
+        }
+        self.out += &format!(" {}:{}\n", span.line, span.column);
+
+        self.depth += 1;
+        walk(self);
+        self.depth -= 1;
+    }
+}
+
+impl<'ast> Visitor<'ast> for Dumper {
+    fn visit_let_statement(&mut self, stmt: &'ast LetStatement) {
+        self.line("LetStatement", "", stmt.span, |d| {
+            visit::walk_let_statement(d, stmt)
+        });
+    }
+    fn visit_const_statement(&mut self, stmt: &'ast ConstStatement) {
+        self.line("ConstStatement", "", stmt.span, |d| {
+            visit::walk_const_statement(d, stmt)
+        });
+    }
+    fn visit_return_statement(&mut self, stmt: &'ast ReturnStatement) {
+        self.line("ReturnStatement", "", stmt.span, |d| {
+            visit::walk_return_statement(d, stmt)
+        });
+    }
+    fn visit_expression_statement(&mut self, stmt: &'ast ExpressionStatement) {
+        self.line("ExpressionStatement", "", stmt.span, |d| {
+            visit::walk_expression_statement(d, stmt)
+        });
+    }
+    fn visit_error_statement(&mut self, stmt: &'ast ErrorStatement) {
+        self.line("ErrorStatement", "", stmt.span, |_| {});
+    }
+    fn visit_block_statement(&mut self, block: &'ast BlockStatement) {
+        self.line("BlockStatement", "", block.span, |d| {
+            visit::walk_block_statement(d, block)
+        });
+    }
+    fn visit_identifier(&mut self, ident: &'ast Identifier) {
+        self.line("Identifier", &ident.value, ident.span, |_| {});
+    }
+    fn visit_integer_literal(&mut self, lit: &'ast IntegerLiteral) {
+        self.line("IntegerLiteral", &lit.value.to_string(), lit.span, |_| {});
+    }
+    fn visit_float_literal(&mut self, lit: &'ast FloatLiteral) {
+        self.line("FloatLiteral", &lit.token.literal, lit.span, |_| {});
+    }
+    fn visit_boolean(&mut self, boolean: &'ast Boolean) {
+        self.line("Boolean", &boolean.value.to_string(), boolean.span, |_| {});
+    }
+    fn visit_string_literal(&mut self, lit: &'ast StringLiteral) {
+        self.line(
+            "StringLiteral",
+            &format!("{:?}", lit.value),
+            lit.span,
+            |_| {},
+        );
+    }
+    fn visit_prefix_expression(&mut self, expr: &'ast PrefixExpression) {
+        self.line("PrefixExpression", &expr.operator, expr.span, |d| {
+            visit::walk_prefix_expression(d, expr)
+        });
+    }
+    fn visit_infix_expression(&mut self, expr: &'ast InfixExpression) {
+        self.line("InfixExpression", &expr.operator, expr.span, |d| {
+            visit::walk_infix_expression(d, expr)
+        });
+    }
+    fn visit_postfix_expression(&mut self, expr: &'ast PostfixExpression) {
+        self.line("PostfixExpression", &expr.operator, expr.span, |d| {
+            visit::walk_postfix_expression(d, expr)
+        });
+    }
+    fn visit_if_expression(&mut self, expr: &'ast IfExpression) {
+        self.line("IfExpression", "", expr.span, |d| {
+            visit::walk_if_expression(d, expr)
+        });
+    }
+    fn visit_function_literal(&mut self, lit: &'ast FunctionLiteral) {
+        self.line("FunctionLiteral", &lit.name, lit.span, |d| {
+            visit::walk_function_literal(d, lit)
+        });
+    }
+    fn visit_call_expression(&mut self, expr: &'ast CallExpression) {
+        self.line("CallExpression", "", expr.span, |d| {
+            visit::walk_call_expression(d, expr)
+        });
+    }
+    fn visit_array_literal(&mut self, lit: &'ast ArrayLiteral) {
+        self.line("ArrayLiteral", "", lit.span, |d| {
+            visit::walk_array_literal(d, lit)
+        });
+    }
+    fn visit_hash_literal(&mut self, lit: &'ast HashLiteral) {
+        self.line("HashLiteral", "", lit.span, |d| {
+            visit::walk_hash_literal(d, lit)
+        });
+    }
+    fn visit_index_expression(&mut self, expr: &'ast IndexExpression) {
+        self.line("IndexExpression", "", expr.span, |d| {
+            visit::walk_index_expression(d, expr)
+        });
+    }
+}
@@ -0,0 +1,186 @@
+use crate::build_tools::token::{Span, TokenType};
+
+use std::error::Error;
+use std::fmt;
+
+/// LexError - everything that can go wrong while lexing. The lexer still produces a token for the
+/// offending source, so lexing carries on, and the parser reports these alongside its own errors.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LexError {
+    /// A string literal reached the end of the input without a closing quote. `span` is the
+    /// opening quote
+    UnterminatedString { span: Span },
+    /// A backslash followed by a character that doesn't start an escape sequence, e.g. `\q`
+    UnknownEscape { sequence: String, span: Span },
+    /// A `\u` escape that isn't `\u{...}` around 1 to 6 hex digits naming a Unicode scalar value
+    InvalidUnicodeEscape { sequence: String, span: Span },
+    /// A `0x`, `0b` or `0o` prefix with no digits after it. `span` is the whole literal
+    MissingDigits { prefix: String, span: Span },
+    /// A character in a number that isn't a digit in its radix, e.g. the `8` in `0o18`
+    InvalidDigit { digit: char, radix: u32, span: Span },
+    /// A `_` digit separator that isn't between two digits, e.g. `1__0` or `100_`. `span` is
+    /// the run of separators
+    MisplacedSeparator { literal: String, span: Span },
+    /// A `/*` comment reached the end of the input before its `*/`. `span` is the outermost
+    /// unclosed `/*`
+    UnterminatedComment { span: Span },
+    /// A character that doesn't begin any token, e.g. `@`
+    UnexpectedCharacter { character: char, span: Span },
+    /// Half of a two character operator on its own: a `&` or `|` that isn't part of `&&` or `||`
+    LoneOperator { operator: char, span: Span },
+}
+
+impl LexError {
+    /// span returns the region of source the error points at
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::UnterminatedString { span }
+            | LexError::UnknownEscape { span, .. }
+            | LexError::InvalidUnicodeEscape { span, .. }
+            | LexError::MissingDigits { span, .. }
+            | LexError::InvalidDigit { span, .. }
+            | LexError::MisplacedSeparator { span, .. }
+            | LexError::UnterminatedComment { span }
+            | LexError::UnexpectedCharacter { span, .. }
+            | LexError::LoneOperator { span, .. } => *span,
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexError::UnterminatedString { .. } => write!(f, "unterminated string literal"),
+            LexError::UnknownEscape { sequence, .. } => {
+                write!(f, "unknown escape sequence `{}`", sequence)
+            }
+            LexError::InvalidUnicodeEscape { sequence, .. } => {
+                write!(f, "invalid unicode escape `{}`", sequence)
+            }
+            LexError::MissingDigits { prefix, .. } => {
+                write!(f, "expected digits after `{}`", prefix)
+            }
+            LexError::InvalidDigit { digit, radix, .. } => write!(
+                f,
+                "invalid digit `{}` in {} literal",
+                digit,
+                radix_name(*radix)
+            ),
+            LexError::MisplacedSeparator { literal, .. } => {
+                write!(f, "misplaced digit separator in `{}`", literal)
+            }
+            LexError::UnterminatedComment { .. } => write!(f, "unterminated block comment"),
+            LexError::UnexpectedCharacter { character, .. } if character.is_control() => {
+                write!(f, "unexpected character `{}`", character.escape_default())
+            }
+            LexError::UnexpectedCharacter { character, .. } => {
+                write!(f, "unexpected character `{}`", character)
+            }
+            LexError::LoneOperator { operator, .. } => write!(
+                f,
+                "unexpected `{}`, did you mean `{}{}`?",
+                operator, operator, operator
+            ),
+        }
+    }
+}
+
+/// radix_name returns the name of a number base the lexer supports, e.g. "hexadecimal" for 16
+pub(crate) fn radix_name(radix: u32) -> &'static str {
+    match radix {
+        2 => "binary",
+        8 => "octal",
+        16 => "hexadecimal",
+        _ => "decimal",
+    }
+}
+
+impl Error for LexError {}
+
+/// ParseError - everything that can go wrong while parsing. Each variant carries the Span of the
+/// offending source, so callers can report it, and can match on the variant to classify it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    /// A specific token was required, but a different one was found
+    UnexpectedToken {
+        expected: TokenType,
+        found: TokenType,
+        literal: String,
+        span: Span,
+    },
+    /// The token found can't begin an expression (there is no prefix parse function for it)
+    MissingPrefixFunction {
+        found: TokenType,
+        literal: String,
+        span: Span,
+    },
+    /// The input ended before a construct was closed. `span` is where the input ended and
+    /// `opened` is the token that began the construct, when it is known
+    Unterminated {
+        expected: TokenType,
+        span: Span,
+        opened: Option<Span>,
+    },
+    /// A literal token whose text doesn't represent a valid value, e.g. an integer that overflows
+    InvalidLiteral {
+        token_type: TokenType,
+        literal: String,
+        span: Span,
+    },
+    /// Expressions nested more than `limit` levels deep, counting each operator applied in a chain
+    /// like `a + b + c` as a level. `span` is the token that went past the limit
+    NestingTooDeep { limit: usize, span: Span },
+    /// An error from the lexer, reported by the parser when it reads the token concerned
+    Lex(LexError),
+}
+
+impl ParseError {
+    /// span returns the region of source the error points at
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::MissingPrefixFunction { span, .. }
+            | ParseError::Unterminated { span, .. }
+            | ParseError::InvalidLiteral { span, .. }
+            | ParseError::NestingTooDeep { span, .. } => *span,
+            ParseError::Lex(error) => error.span(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedToken {
+                expected,
+                found,
+                literal,
+                ..
+            } => write!(f, "expected {}, found {} `{}`", expected, found, literal),
+            ParseError::MissingPrefixFunction { found, literal, .. } => {
+                write!(f, "expected an expression, found {} `{}`", found, literal)
+            }
+            ParseError::Unterminated { expected, .. } => {
+                write!(f, "unexpected end of input, expected {}", expected)
+            }
+            ParseError::InvalidLiteral {
+                token_type,
+                literal,
+                ..
+            } => write!(f, "invalid {} literal `{}`", token_type, literal),
+            ParseError::NestingTooDeep { limit, .. } => {
+                write!(f, "expression nested more than {} levels deep", limit)
+            }
+            ParseError::Lex(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseError::Lex(error) => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,462 @@
+use crate::build_tools::ast::*;
+use crate::build_tools::token::{Span, Token};
+
+use std::rc::Rc;
+
+/// Folder rewrites an AST by taking ownership of each node and returning its replacement. Every
+/// method defaults to its matching fold_* function, which folds the node's children and rebuilds
+/// it, so a pass only overrides the node kinds it cares about. Passes that change a node's kind
+/// (e.g. constant folding an InfixExpression into an IntegerLiteral) override fold_expression or
+/// fold_statement.
+pub trait Folder {
+    fn fold_program(&mut self, program: RootNode) -> RootNode {
+        fold_program(self, program)
+    }
+    fn fold_statement(&mut self, stmt: Statement) -> Statement {
+        fold_statement(self, stmt)
+    }
+    fn fold_let_statement(&mut self, stmt: LetStatement) -> LetStatement {
+        fold_let_statement(self, stmt)
+    }
+    fn fold_const_statement(&mut self, stmt: ConstStatement) -> ConstStatement {
+        fold_const_statement(self, stmt)
+    }
+    fn fold_return_statement(&mut self, stmt: ReturnStatement) -> ReturnStatement {
+        fold_return_statement(self, stmt)
+    }
+    fn fold_expression_statement(&mut self, stmt: ExpressionStatement) -> ExpressionStatement {
+        fold_expression_statement(self, stmt)
+    }
+    fn fold_error_statement(&mut self, stmt: ErrorStatement) -> ErrorStatement {
+        fold_error_statement(self, stmt)
+    }
+    fn fold_block_statement(&mut self, block: BlockStatement) -> BlockStatement {
+        fold_block_statement(self, block)
+    }
+    fn fold_expression(&mut self, expr: Expression) -> Expression {
+        fold_expression(self, expr)
+    }
+    fn fold_identifier(&mut self, ident: Identifier) -> Identifier {
+        fold_identifier(self, ident)
+    }
+    fn fold_integer_literal(&mut self, lit: IntegerLiteral) -> IntegerLiteral {
+        fold_integer_literal(self, lit)
+    }
+    fn fold_float_literal(&mut self, lit: FloatLiteral) -> FloatLiteral {
+        fold_float_literal(self, lit)
+    }
+    fn fold_boolean(&mut self, boolean: Boolean) -> Boolean {
+        fold_boolean(self, boolean)
+    }
+    fn fold_string_literal(&mut self, lit: StringLiteral) -> StringLiteral {
+        fold_string_literal(self, lit)
+    }
+    fn fold_prefix_expression(&mut self, expr: PrefixExpression) -> PrefixExpression {
+        fold_prefix_expression(self, expr)
+    }
+    fn fold_infix_expression(&mut self, expr: InfixExpression) -> InfixExpression {
+        fold_infix_expression(self, expr)
+    }
+    fn fold_postfix_expression(&mut self, expr: PostfixExpression) -> PostfixExpression {
+        fold_postfix_expression(self, expr)
+    }
+    fn fold_if_expression(&mut self, expr: IfExpression) -> IfExpression {
+        fold_if_expression(self, expr)
+    }
+    fn fold_function_literal(&mut self, lit: FunctionLiteral) -> FunctionLiteral {
+        fold_function_literal(self, lit)
+    }
+    fn fold_call_expression(&mut self, expr: CallExpression) -> CallExpression {
+        fold_call_expression(self, expr)
+    }
+    fn fold_array_literal(&mut self, lit: ArrayLiteral) -> ArrayLiteral {
+        fold_array_literal(self, lit)
+    }
+    fn fold_hash_literal(&mut self, lit: HashLiteral) -> HashLiteral {
+        fold_hash_literal(self, lit)
+    }
+    fn fold_index_expression(&mut self, expr: IndexExpression) -> IndexExpression {
+        fold_index_expression(self, expr)
+    }
+    fn fold_token(&mut self, token: Token) -> Token {
+        fold_token(self, token)
+    }
+    /// fold_span is called for the span of every node and token, so a pass can rewrite or strip
+    /// source positions in one place
+    fn fold_span(&mut self, span: Span) -> Span {
+        span
+    }
+}
+
+pub fn fold_program<F: Folder + ?Sized>(folder: &mut F, program: RootNode) -> RootNode {
+    RootNode {
+        statements: program
+            .statements
+            .into_iter()
+            .map(|stmt| folder.fold_statement(stmt))
+            .collect(),
+    }
+}
+
+pub fn fold_statement<F: Folder + ?Sized>(folder: &mut F, stmt: Statement) -> Statement {
+    match stmt {
+        Statement::Let(stmt) => Statement::Let(folder.fold_let_statement(stmt)),
+        Statement::Const(stmt) => Statement::Const(folder.fold_const_statement(stmt)),
+        Statement::Return(stmt) => Statement::Return(folder.fold_return_statement(stmt)),
+        Statement::Expression(stmt) => {
+            Statement::Expression(folder.fold_expression_statement(stmt))
+        }
+        Statement::Error(stmt) => Statement::Error(folder.fold_error_statement(stmt)),
+    }
+}
+
+pub fn fold_let_statement<F: Folder + ?Sized>(folder: &mut F, stmt: LetStatement) -> LetStatement {
+    LetStatement {
+        token: folder.fold_token(stmt.token),
+        span: folder.fold_span(stmt.span),
+        name: folder.fold_identifier(stmt.name),
+        value: folder.fold_expression(stmt.value),
+        doc: stmt.doc,
+    }
+}
+
+pub fn fold_const_statement<F: Folder + ?Sized>(
+    folder: &mut F,
+    stmt: ConstStatement,
+) -> ConstStatement {
+    ConstStatement {
+        token: folder.fold_token(stmt.token),
+        span: folder.fold_span(stmt.span),
+        name: folder.fold_identifier(stmt.name),
+        value: folder.fold_expression(stmt.value),
+        doc: stmt.doc,
+    }
+}
+
+pub fn fold_return_statement<F: Folder + ?Sized>(
+    folder: &mut F,
+    stmt: ReturnStatement,
+) -> ReturnStatement {
+    ReturnStatement {
+        token: folder.fold_token(stmt.token),
+        span: folder.fold_span(stmt.span),
+        return_value: folder.fold_expression(stmt.return_value),
+    }
+}
+
+pub fn fold_expression_statement<F: Folder + ?Sized>(
+    folder: &mut F,
+    stmt: ExpressionStatement,
+) -> ExpressionStatement {
+    ExpressionStatement {
+        token: folder.fold_token(stmt.token),
+        span: folder.fold_span(stmt.span),
+        expression: folder.fold_expression(stmt.expression),
+    }
+}
+
+pub fn fold_error_statement<F: Folder + ?Sized>(
+    folder: &mut F,
+    stmt: ErrorStatement,
+) -> ErrorStatement {
+    ErrorStatement {
+        token: folder.fold_token(stmt.token),
+        span: folder.fold_span(stmt.span),
+    }
+}
+
+pub fn fold_block_statement<F: Folder + ?Sized>(
+    folder: &mut F,
+    block: BlockStatement,
+) -> BlockStatement {
+    BlockStatement {
+        token: folder.fold_token(block.token),
+        span: folder.fold_span(block.span),
+        statements: block
+            .statements
+            .into_iter()
+            .map(|stmt| folder.fold_statement(stmt))
+            .collect(),
+    }
+}
+
+pub fn fold_expression<F: Folder + ?Sized>(folder: &mut F, expr: Expression) -> Expression {
+    match expr {
+        Expression::Identifier(ident) => Expression::Identifier(folder.fold_identifier(ident)),
+        Expression::Integer(lit) => Expression::Integer(folder.fold_integer_literal(lit)),
+        Expression::Float(lit) => Expression::Float(folder.fold_float_literal(lit)),
+        Expression::Boolean(boolean) => Expression::Boolean(folder.fold_boolean(boolean)),
+        Expression::String(lit) => Expression::String(folder.fold_string_literal(lit)),
+        Expression::Prefix(expr) => Expression::Prefix(folder.fold_prefix_expression(expr)),
+        Expression::Infix(expr) => Expression::Infix(folder.fold_infix_expression(expr)),
+        Expression::Postfix(expr) => Expression::Postfix(folder.fold_postfix_expression(expr)),
+        Expression::If(expr) => Expression::If(folder.fold_if_expression(expr)),
+        Expression::Function(lit) => Expression::Function(folder.fold_function_literal(lit)),
+        Expression::Call(expr) => Expression::Call(folder.fold_call_expression(expr)),
+        Expression::Array(lit) => Expression::Array(folder.fold_array_literal(lit)),
+        Expression::Hash(lit) => Expression::Hash(folder.fold_hash_literal(lit)),
+        Expression::Index(expr) => Expression::Index(folder.fold_index_expression(expr)),
+    }
+}
+
+pub fn fold_identifier<F: Folder + ?Sized>(folder: &mut F, ident: Identifier) -> Identifier {
+    Identifier {
+        token: folder.fold_token(ident.token),
+        span: folder.fold_span(ident.span),
+        value: ident.value,
+    }
+}
+
+pub fn fold_integer_literal<F: Folder + ?Sized>(
+    folder: &mut F,
+    lit: IntegerLiteral,
+) -> IntegerLiteral {
+    IntegerLiteral {
+        token: folder.fold_token(lit.token),
+        span: folder.fold_span(lit.span),
+        value: lit.value,
+    }
+}
+
+pub fn fold_float_literal<F: Folder + ?Sized>(folder: &mut F, lit: FloatLiteral) -> FloatLiteral {
+    FloatLiteral {
+        token: folder.fold_token(lit.token),
+        span: folder.fold_span(lit.span),
+        value: lit.value,
+    }
+}
+
+pub fn fold_boolean<F: Folder + ?Sized>(folder: &mut F, boolean: Boolean) -> Boolean {
+    Boolean {
+        token: folder.fold_token(boolean.token),
+        span: folder.fold_span(boolean.span),
+        value: boolean.value,
+    }
+}
+
+pub fn fold_string_literal<F: Folder + ?Sized>(
+    folder: &mut F,
+    lit: StringLiteral,
+) -> StringLiteral {
+    StringLiteral {
+        token: folder.fold_token(lit.token),
+        span: folder.fold_span(lit.span),
+        value: lit.value,
+    }
+}
+
+pub fn fold_prefix_expression<F: Folder + ?Sized>(
+    folder: &mut F,
+    expr: PrefixExpression,
+) -> PrefixExpression {
+    PrefixExpression {
+        token: folder.fold_token(expr.token),
+        span: folder.fold_span(expr.span),
+        operator: expr.operator,
+        right: Box::new(folder.fold_expression(*expr.right)),
+    }
+}
+
+pub fn fold_infix_expression<F: Folder + ?Sized>(
+    folder: &mut F,
+    expr: InfixExpression,
+) -> InfixExpression {
+    InfixExpression {
+        token: folder.fold_token(expr.token),
+        span: folder.fold_span(expr.span),
+        left: Box::new(folder.fold_expression(*expr.left)),
+        operator: expr.operator,
+        right: Box::new(folder.fold_expression(*expr.right)),
+    }
+}
+
+pub fn fold_postfix_expression<F: Folder + ?Sized>(
+    folder: &mut F,
+    expr: PostfixExpression,
+) -> PostfixExpression {
+    PostfixExpression {
+        token: folder.fold_token(expr.token),
+        span: folder.fold_span(expr.span),
+        left: folder.fold_identifier(expr.left),
+        operator: expr.operator,
+    }
+}
+
+pub fn fold_if_expression<F: Folder + ?Sized>(folder: &mut F, expr: IfExpression) -> IfExpression {
+    IfExpression {
+        token: folder.fold_token(expr.token),
+        span: folder.fold_span(expr.span),
+        condition: Box::new(folder.fold_expression(*expr.condition)),
+        consequence: folder.fold_block_statement(expr.consequence),
+        alternative: expr
+            .alternative
+            .map(|alternative| folder.fold_block_statement(alternative)),
+    }
+}
+
+pub fn fold_function_literal<F: Folder + ?Sized>(
+    folder: &mut F,
+    lit: FunctionLiteral,
+) -> FunctionLiteral {
+    // The body is shared with any Function objects created from this literal, so only clone it
+    // when it is not uniquely owned
+    let body = Rc::try_unwrap(lit.body).unwrap_or_else(|body| (*body).clone());
+
+    FunctionLiteral {
+        token: folder.fold_token(lit.token),
+        span: folder.fold_span(lit.span),
+        parameters: lit
+            .parameters
+            .into_iter()
+            .map(|param| folder.fold_identifier(param))
+            .collect(),
+        body: Rc::new(folder.fold_block_statement(body)),
+        name: lit.name,
+    }
+}
+
+pub fn fold_call_expression<F: Folder + ?Sized>(
+    folder: &mut F,
+    expr: CallExpression,
+) -> CallExpression {
+    CallExpression {
+        token: folder.fold_token(expr.token),
+        span: folder.fold_span(expr.span),
+        function: Box::new(folder.fold_expression(*expr.function)),
+        arguments: expr
+            .arguments
+            .into_iter()
+            .map(|arg| folder.fold_expression(arg))
+            .collect(),
+    }
+}
+
+pub fn fold_array_literal<F: Folder + ?Sized>(folder: &mut F, lit: ArrayLiteral) -> ArrayLiteral {
+    ArrayLiteral {
+        token: folder.fold_token(lit.token),
+        span: folder.fold_span(lit.span),
+        elements: lit
+            .elements
+            .into_iter()
+            .map(|element| folder.fold_expression(element))
+            .collect(),
+    }
+}
+
+pub fn fold_hash_literal<F: Folder + ?Sized>(folder: &mut F, lit: HashLiteral) -> HashLiteral {
+    HashLiteral {
+        token: folder.fold_token(lit.token),
+        span: folder.fold_span(lit.span),
+        pairs: lit
+            .pairs
+            .into_iter()
+            .map(|(key, value)| (folder.fold_expression(key), folder.fold_expression(value)))
+            .collect(),
+    }
+}
+
+pub fn fold_index_expression<F: Folder + ?Sized>(
+    folder: &mut F,
+    expr: IndexExpression,
+) -> IndexExpression {
+    IndexExpression {
+        token: folder.fold_token(expr.token),
+        span: folder.fold_span(expr.span),
+        left: Box::new(folder.fold_expression(*expr.left)),
+        index: Box::new(folder.fold_expression(*expr.index)),
+    }
+}
+
+pub fn fold_token<F: Folder + ?Sized>(folder: &mut F, token: Token) -> Token {
+    Token {
+        token_type: token.token_type,
+        literal: token.literal,
+        span: folder.fold_span(token.span),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_tools::lexer::Lexer;
+    use crate::build_tools::parser::Parser;
+    use crate::build_tools::token::TokenType;
+
+    /// ConstantFold replaces the sum of two integer literals with a literal holding the result
+    struct ConstantFold;
+
+    impl Folder for ConstantFold {
+        fn fold_expression(&mut self, expr: Expression) -> Expression {
+            let expr = fold_expression(self, expr);
+
+            let value = match &expr {
+                Expression::Infix(infix) if infix.operator == "+" => {
+                    match (&*infix.left, &*infix.right) {
+                        (Expression::Integer(left), Expression::Integer(right)) => {
+                            left.value.checked_add(right.value)
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+
+            match value {
+                Some(value) => Expression::Integer(IntegerLiteral {
+                    token: Token {
+                        token_type: TokenType::INTEGER,
+                        literal: value.to_string(),
+                        span: expr.span(),
+                    },
+                    span: expr.span(),
+                    value,
+                }),
+                None => expr,
+            }
+        }
+    }
+
+    /// ClearSpans sets every span to the default, so trees parsed from different text compare equal
+    /// when they have the same structure
+    struct ClearSpans;
+
+    impl Folder for ClearSpans {
+        fn fold_span(&mut self, _span: Span) -> Span {
+            Span::default()
+        }
+    }
+
+    fn parse(source: &str) -> RootNode {
+        let mut parser = Parser::new(Lexer::new(source));
+        let program = parser.parse_program();
+        assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+
+        program
+    }
+
+    #[test]
+    fn folds_constants_in_a_nested_block() {
+        let program = parse(
+            "let f = func(x) { if (x) { return [1 + 2, x + 2, 1 * 2, \"1\" + \"2\"]; } else { x } };",
+        );
+        let folded = ConstantFold.fold_program(program);
+
+        assert_eq!(
+            folded.string(),
+            "let f = func(x) { if (x) { return [3, (x + 2), (1 * 2), (\"1\" + \"2\")]; } else { x; }; };"
+        );
+        assert_eq!(
+            ClearSpans.fold_program(folded),
+            ClearSpans.fold_program(parse(
+                "let f = func(x) { if (x) { return [3, x + 2, 1 * 2, \"1\" + \"2\"]; } else { x } };"
+            ))
+        );
+    }
+
+    #[test]
+    fn leaves_other_nodes_unchanged() {
+        let program = parse("let f = func(x, y) { y++; f(x - 1, {y: [y * 2]})[0] };\nf(1, 2.5);");
+
+        assert_eq!(ConstantFold.fold_program(program.clone()), program);
+    }
+}
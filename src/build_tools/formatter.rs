@@ -0,0 +1,556 @@
+use crate::build_tools::ast::*;
+use crate::build_tools::error::ParseError;
+use crate::build_tools::lexer::Lexer;
+use crate::build_tools::parser::{self, Parser, CALL, PREFIX};
+use crate::build_tools::token::{Comment, Span};
+
+/// One level of indentation
+static INDENT: &str = "    ";
+
+/// format parses `source` and returns it in canonical Monkey style: four space indentation, one
+/// space around infix operators, only the parentheses precedence requires, and a trailing comma
+/// after every element of multi-line array and hash literals. Comments are kept, and runs of blank
+/// lines between statements collapse to one. Blocks and literals written on a single line stay on
+/// one line, the rest get one statement or element per line.
+///
+/// A program with parse errors can't be formatted, so its errors are returned instead.
+pub fn format(source: &str) -> Result<String, Vec<ParseError>> {
+    let mut parser = Parser::new(Lexer::new(source));
+    let program = parser.parse_program();
+
+    if !parser.errors().is_empty() {
+        return Err(parser.errors().to_vec());
+    }
+
+    let mut formatter = Formatter {
+        source,
+        comments: parser.comments(),
+        next_comment: 0,
+        indent: 0,
+        at_block_start: true,
+        last_end: 0,
+        out: String::new(),
+    };
+
+    formatter.statements(&program.statements, source.len(), false);
+
+    Ok(formatter.out)
+}
+
+/// Formatter writes an AST back out as source, weaving the comments the lexer collected back in
+/// by position
+struct Formatter<'a> {
+    source: &'a str,
+    comments: &'a [Comment],
+    /// Index of the first comment not written yet
+    next_comment: usize,
+    indent: usize,
+    /// Set right after a `{` or `[` that starts a multi-line block or literal, where blank lines
+    /// are never kept
+    at_block_start: bool,
+    /// Byte offset in source of the end of the last statement, element or comment written
+    last_end: usize,
+    out: String,
+}
+
+impl<'a> Formatter<'a> {
+    /// statements writes one statement per line, followed by any comments left before `end`.
+    /// Statements in a block don't need a `;` after the last expression, it's the block's value
+    fn statements(&mut self, statements: &[Statement], end: usize, in_block: bool) {
+        let mut optional_semicolon = None;
+
+        for (i, stmt) in statements.iter().enumerate() {
+            let span = stmt.span();
+
+            self.start_item(span.start);
+            self.statement_in_sequence(
+                stmt,
+                in_block && i + 1 == statements.len(),
+                &mut optional_semicolon,
+            );
+            self.end_item(span.end);
+        }
+
+        self.comments_before(end);
+    }
+
+    /// statement_in_sequence writes a statement that follows the ones before it. An `if`
+    /// expression statement doesn't need a `;`, so `optional_semicolon` records where it would
+    /// go, and it is only added should the next statement start with something that would
+    /// otherwise continue the `if`, e.g. `-1` or `[0]`
+    fn statement_in_sequence(
+        &mut self,
+        stmt: &Statement,
+        block_value: bool,
+        optional_semicolon: &mut Option<usize>,
+    ) {
+        let start = self.out.len();
+        self.statement(stmt, block_value);
+
+        if let Some(at) = optional_semicolon.take() {
+            if self.out[start..].starts_with(&['-', '(', '[', '!'][..]) {
+                self.out.insert(at, ';');
+            }
+        }
+
+        if let Statement::Expression(ExpressionStatement {
+            expression: Expression::If(_),
+            ..
+        }) = stmt
+        {
+            *optional_semicolon = Some(self.out.len());
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement, block_value: bool) {
+        match stmt {
+            Statement::Let(stmt) => self.binding("let", &stmt.name, &stmt.value),
+            Statement::Const(stmt) => self.binding("const", &stmt.name, &stmt.value),
+            Statement::Return(stmt) => {
+                self.out += "return ";
+                self.expression(&stmt.return_value);
+                self.out.push(';');
+            }
+            Statement::Expression(stmt) => {
+                self.expression(&stmt.expression);
+
+                // statement_in_sequence adds the `;` after an `if` when it is needed
+                if !block_value && !matches!(stmt.expression, Expression::If(_)) {
+                    self.out.push(';');
+                }
+            }
+            // format refuses programs with errors, but should one get here keep it as written
+            Statement::Error(stmt) => self.out += &self.source[stmt.span.start..stmt.span.end],
+        }
+    }
+
+    fn binding(&mut self, keyword: &str, name: &Identifier, value: &Expression) {
+        self.out += keyword;
+        self.out.push(' ');
+        self.out += &name.value;
+        self.out += " = ";
+        self.expression(value);
+        self.out.push(';');
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Identifier(ident) => self.out += &ident.value,
+            // Literals are kept exactly as written
+            Expression::Integer(lit) => self.out += &self.source[lit.span.start..lit.span.end],
+            Expression::Float(lit) => self.out += &self.source[lit.span.start..lit.span.end],
+            Expression::String(lit) => self.out += &self.source[lit.span.start..lit.span.end],
+            Expression::Boolean(boolean) => self.out += &boolean.token.literal,
+            Expression::Prefix(prefix) => {
+                self.out += &prefix.operator;
+
+                // `- -x` must not be written `--x`, which lexes as a decrement
+                let doubled_minus = prefix.operator == "-"
+                    && matches!(&*prefix.right, Expression::Prefix(right) if right.operator == "-");
+
+                self.operand(
+                    &prefix.right,
+                    doubled_minus || expression_precedence(&prefix.right) < PREFIX,
+                );
+            }
+            Expression::Infix(infix) => {
+                let precedence = parser::precedence(infix.token.token_type);
+
+                // Infix operators are left associative, so only a right operand of equal
+                // precedence needs parentheses
+                self.operand(&infix.left, expression_precedence(&infix.left) < precedence);
+                self.out.push(' ');
+                self.out += &infix.operator;
+                self.out.push(' ');
+                self.operand(
+                    &infix.right,
+                    expression_precedence(&infix.right) <= precedence,
+                );
+            }
+            Expression::Postfix(postfix) => {
+                self.out += &postfix.left.value;
+                self.out += &postfix.operator;
+            }
+            Expression::If(if_expr) => {
+                self.out += "if (";
+                self.expression(&if_expr.condition);
+                self.out += ") ";
+                self.block(&if_expr.consequence);
+
+                if let Some(alternative) = &if_expr.alternative {
+                    self.out += " else ";
+                    self.block(alternative);
+                }
+            }
+            Expression::Function(lit) => {
+                let parameters: Vec<&str> = lit.parameters.iter().map(|p| &p.value[..]).collect();
+
+                self.out += "func(";
+                self.out += &parameters.join(", ");
+                self.out += ") ";
+                self.block(&lit.body);
+            }
+            Expression::Call(call) => {
+                self.operand(&call.function, expression_precedence(&call.function) < CALL);
+                self.out.push('(');
+
+                for (i, arg) in call.arguments.iter().enumerate() {
+                    if i > 0 {
+                        self.out += ", ";
+                    }
+                    self.expression(arg);
+                }
+
+                self.out.push(')');
+            }
+            Expression::Index(index) => {
+                self.operand(&index.left, expression_precedence(&index.left) < CALL);
+                self.out.push('[');
+                self.expression(&index.index);
+                self.out.push(']');
+            }
+            Expression::Array(array) => self.delimited(
+                ("[", "]"),
+                array.span,
+                &array.elements,
+                |element| element.span(),
+                |formatter, element| formatter.expression(element),
+            ),
+            Expression::Hash(hash) => self.delimited(
+                ("{", "}"),
+                hash.span,
+                &hash.pairs,
+                |(key, value)| key.span().to(value.span()),
+                |formatter, (key, value)| {
+                    formatter.expression(key);
+                    formatter.out += ": ";
+                    formatter.expression(value);
+                },
+            ),
+        }
+    }
+
+    fn operand(&mut self, expr: &Expression, parenthesize: bool) {
+        if parenthesize {
+            self.out.push('(');
+            self.expression(expr);
+            self.out.push(')');
+        } else {
+            self.expression(expr);
+        }
+    }
+
+    fn block(&mut self, block: &BlockStatement) {
+        if block.statements.is_empty() && !self.has_comments_within(block.span) {
+            self.out += "{}";
+            return;
+        }
+
+        if self.is_one_line(block.span) {
+            self.out += "{ ";
+
+            let mut optional_semicolon = None;
+
+            for (i, stmt) in block.statements.iter().enumerate() {
+                if i > 0 {
+                    self.out.push(' ');
+                }
+                self.statement_in_sequence(
+                    stmt,
+                    i + 1 == block.statements.len(),
+                    &mut optional_semicolon,
+                );
+            }
+
+            self.out += " }";
+            return;
+        }
+
+        self.out.push('{');
+        self.open();
+        self.statements(&block.statements, block.span.end, true);
+        self.close("}");
+    }
+
+    /// delimited writes a comma separated array or hash literal. One written on multiple lines
+    /// gets one item per line, each followed by a comma
+    fn delimited<T>(
+        &mut self,
+        (open, close): (&str, &str),
+        span: Span,
+        items: &[T],
+        item_span: impl Fn(&T) -> Span,
+        mut write_item: impl FnMut(&mut Self, &T),
+    ) {
+        self.out += open;
+
+        if self.is_one_line(span) || (items.is_empty() && !self.has_comments_within(span)) {
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    self.out += ", ";
+                }
+                write_item(self, item);
+            }
+
+            self.out += close;
+            return;
+        }
+
+        self.open();
+
+        for item in items {
+            let span = item_span(item);
+
+            self.start_item(span.start);
+            write_item(self, item);
+            self.out.push(',');
+            self.end_item(span.end);
+        }
+
+        self.comments_before(span.end);
+        self.close(close);
+    }
+
+    /// open starts the body of a multi-line block or literal, after its opening delimiter
+    fn open(&mut self) {
+        self.out.push('\n');
+        self.indent += 1;
+        self.at_block_start = true;
+    }
+
+    /// close ends the body of a multi-line block or literal with its closing delimiter
+    fn close(&mut self, delimiter: &str) {
+        self.indent -= 1;
+        self.write_indent();
+        self.out += delimiter;
+    }
+
+    /// start_item begins a line holding a statement or literal element that starts at `start`,
+    /// after writing the comments that come before it
+    fn start_item(&mut self, start: usize) {
+        self.comments_before(start);
+        self.blank_line_before(start);
+        self.write_indent();
+    }
+
+    /// end_item finishes the line of a statement or literal element that ended at `end`,
+    /// keeping any comments written on the same line
+    fn end_item(&mut self, end: usize) {
+        self.last_end = end;
+        self.trailing_comments();
+        self.out.push('\n');
+    }
+
+    /// comments_before writes, one per line, every comment not yet written that starts before
+    /// `position`
+    fn comments_before(&mut self, position: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.span.start >= position {
+                break;
+            }
+
+            self.next_comment += 1;
+            self.blank_line_before(comment.span.start);
+            self.write_indent();
+            self.out += &comment.text;
+            self.out.push('\n');
+            self.last_end = comment.span.end;
+        }
+    }
+
+    /// trailing_comments appends the comments that follow the last item on the same line. Comments
+    /// from inside an expression that is written on one line end up here too, so none are lost
+    fn trailing_comments(&mut self) {
+        let mut after_line_comment = false;
+
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            let inside = comment.span.start < self.last_end;
+            if !inside && self.source[self.last_end..comment.span.start].contains('\n') {
+                break;
+            }
+
+            self.next_comment += 1;
+
+            // Nothing can follow a `//` comment on its line
+            if after_line_comment {
+                self.out.push('\n');
+                self.write_indent();
+            } else {
+                self.out.push(' ');
+            }
+
+            self.out += &comment.text;
+            self.last_end = self.last_end.max(comment.span.end);
+            after_line_comment = comment.text.starts_with("//");
+        }
+    }
+
+    /// blank_line_before keeps a single blank line where the source has one or more between the
+    /// last item and the one starting at `start`
+    fn blank_line_before(&mut self, start: usize) {
+        if self.at_block_start {
+            self.at_block_start = false;
+            return;
+        }
+
+        if start > self.last_end && self.source[self.last_end..start].matches('\n').count() > 1 {
+            self.out.push('\n');
+        }
+    }
+
+    fn write_indent(&mut self) {
+        for _ in 0..self.indent {
+            self.out += INDENT;
+        }
+    }
+
+    /// is_one_line reports whether `span` was written on a single line without comments inside
+    fn is_one_line(&self, span: Span) -> bool {
+        !self.source[span.start..span.end].contains('\n') && !self.has_comments_within(span)
+    }
+
+    /// has_comments_within reports whether any comment not yet written starts inside `span`
+    fn has_comments_within(&self, span: Span) -> bool {
+        self.comments[self.next_comment..]
+            .iter()
+            .any(|c| c.span.start >= span.start && c.span.start < span.end)
+    }
+}
+
+/// expression_precedence returns how tightly an expression binds when it is the operand of an
+/// operator. Anything other than a prefix or infix expression never needs parentheses
+fn expression_precedence(expr: &Expression) -> usize {
+    match expr {
+        Expression::Infix(infix) => parser::precedence(infix.token.token_type),
+        Expression::Prefix(_) => PREFIX,
+        _ => usize::MAX,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> RootNode {
+        let mut parser = Parser::new(Lexer::new(source));
+        let program = parser.parse_program();
+        assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+
+        program
+    }
+
+    static MESSY: &str = "// header comment
+let   add=func(a,b){a+b};
+
+
+
+const limit = ((1 + 2)) * 3;
+let xs = [1,
+  2, 3];
+let h = {\"a\": 1, \"b\": [1, 2]};
+if (x > 1) { return -(a); } else { let y = !true; y }
+/// Doc for f
+let f = func(x) {
+    // inside
+    let y = x++;
+
+    y * (x - 1) // trailing
+};
+f(1)[0]
+";
+
+    #[test]
+    fn format_canonical_style() {
+        assert_eq!(
+            format(MESSY).unwrap(),
+            "// header comment
+let add = func(a, b) { a + b };
+
+const limit = (1 + 2) * 3;
+let xs = [
+    1,
+    2,
+    3,
+];
+let h = {\"a\": 1, \"b\": [1, 2]};
+if (x > 1) { return -a; } else { let y = !true; y }
+/// Doc for f
+let f = func(x) {
+    // inside
+    let y = x++;
+
+    y * (x - 1) // trailing
+};
+f(1)[0];
+"
+        );
+    }
+
+    #[test]
+    fn format_keeps_only_needed_parentheses() {
+        let tests = [
+            ("(a + b) * c;", "(a + b) * c;\n"),
+            ("a + (b * c);", "a + b * c;\n"),
+            ("a - (b - c);", "a - (b - c);\n"),
+            ("(a - b) - c;", "a - b - c;\n"),
+            ("(a || b) && c;", "(a || b) && c;\n"),
+            ("a || (b && c);", "a || b && c;\n"),
+            ("-(-a);", "-(-a);\n"),
+            ("(func(x) { x })(1);", "func(x) { x }(1);\n"),
+        ];
+
+        for (source, expected) in tests.iter() {
+            assert_eq!(format(source).unwrap(), *expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn format_is_idempotent() {
+        let sources = [
+            MESSY,
+            "",
+            "// only a comment",
+            "let a = 1;\n\n\n\nlet b = 2;",
+            "if (a) {\n1\n} else {\n2\n}",
+            "let h = {\n\"a\": [1,\n2],\n};",
+            "/* block\n   comment */ let x = 1; // after",
+        ];
+
+        for source in sources.iter() {
+            let once = format(source).unwrap();
+            assert_eq!(format(&once).unwrap(), once, "{}", source);
+        }
+    }
+
+    #[test]
+    fn format_preserves_the_program() {
+        let sources = [
+            MESSY,
+            "let x = 5;\nif (false) { 1 };\n-1",
+            "if (true) { 1 };\n[0]",
+            "if (a) { 1 };\n(b + c) * d;\nif (e) { 2 };\n!f",
+            "if (a) { 1 }\nlet b = 2;\nif (b) { 3 }\nb",
+            "let f = func() { if (a) { 1 }; -2 };",
+            "if (a) { 1 } else { 2 } - 1;",
+        ];
+
+        for source in sources.iter() {
+            let formatted = format(source).unwrap();
+
+            assert_eq!(
+                parse(&formatted).string(),
+                parse(source).string(),
+                "{:?} formatted as {:?}",
+                source,
+                formatted
+            );
+        }
+    }
+
+    #[test]
+    fn format_rejects_parse_errors() {
+        let errors = format("let x = ;\nlet = 2;").unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+    }
+}
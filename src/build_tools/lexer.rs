@@ -1,362 +1,1089 @@
+use crate::build_tools::error::LexError;
 use crate::build_tools::token::*;
+use crate::build_tools::unicode;
 
-#[derive(Clone)]
-pub struct Lexer {
-    input: Vec<char>,
+use std::iter::FusedIterator;
+use std::ops::Range;
+
+/// Lexer turns Monkey source into Tokens. It borrows the source and tracks its position as byte
+/// offsets into it, so advancing is O(1) and identifiers/literals are sliced straight out of the input
+pub struct Lexer<'a> {
+    input: &'a str,
+    /// The character at position, or '\0' once the input is used up. A NUL can also appear in the
+    /// input itself, so use at_end to tell whether the input is used up
     current_char: char,
+    /// Byte offset of current_char
     position: usize,
+    /// Byte offset of the character after current_char
     read_position: usize,
+    /// 1-based line of current_char
     line: usize,
+    /// 1-based column of current_char, counted in characters rather than bytes
+    column: usize,
+    /// Every comment skipped so far, in source order
+    comments: Vec<Comment>,
+    /// Whether whitespace and comments are returned as WHITESPACE and COMMENT tokens
+    trivia: bool,
+    /// Errors found since they were last taken
+    errors: Vec<LexError>,
 }
 
-impl Lexer {
-    pub fn new(input: String) -> Lexer {
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Lexer<'a> {
         let mut lexer = Lexer {
-            input: input.chars().collect(),
+            input,
             current_char: 'a',
             position: 0,
             read_position: 0,
-            line: 0,
+            line: 1,
+            column: 0,
+            comments: vec![],
+            trivia: false,
+            errors: vec![],
         };
 
         lexer.read_char();
         lexer
     }
 
+    /// with_trivia returns a Lexer that also produces WHITESPACE and COMMENT tokens instead of
+    /// skipping them. Every byte of the input then belongs to exactly one token, so the tokens'
+    /// spans tile the input and their source text concatenates back to it exactly
+    pub fn with_trivia(input: &'a str) -> Lexer<'a> {
+        let mut lexer = Lexer::new(input);
+        lexer.trivia = true;
+        lexer
+    }
+
     fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
-            self.current_char = '\0';
-        } else {
-            self.current_char = self.input[self.read_position];
+        // Lines are tracked here rather than in skip_whitespace, so they stay correct after
+        // comments and inside multi-line strings
+        if self.current_char == '\n' {
+            self.line += 1;
+            self.column = 0;
         }
 
         self.position = self.read_position;
-        self.read_position += 1;
+        self.column += 1;
+
+        match self.input[self.read_position..].chars().next() {
+            Some(ch) => {
+                self.current_char = ch;
+                self.read_position += ch.len_utf8();
+            }
+            None => self.current_char = '\0',
+        }
     }
 
+    /// at_end reports whether every character of the input has been read
+    fn at_end(&self) -> bool {
+        self.position >= self.input.len()
+    }
+
+    /// read_string reads a string literal and returns its value with escape sequences decoded. A
+    /// bad escape is kept as written and reported, as is a string that is never closed
     fn read_string(&mut self) -> String {
-        let position: usize = self.position + 1;
+        let opening = Span::new(self.position, self.read_position, self.line, self.column());
+        let mut value = String::new();
 
         loop {
             self.read_char();
-            if self.current_char == '"' || self.current_char == '\0' {
-                break;
+
+            match self.current_char {
+                _ if self.at_end() => {
+                    self.errors
+                        .push(LexError::UnterminatedString { span: opening });
+                    break;
+                }
+                '"' => break,
+                '\\' => self.read_escape(&mut value),
+                ch => value.push(ch),
             }
         }
 
-        let string: String = self.input[position..self.position].iter().collect();
+        value
+    }
+
+    /// read_escape decodes the escape sequence starting at the backslash in current_char onto
+    /// `value`, leaving current_char on the escape's last character
+    fn read_escape(&mut self, value: &mut String) {
+        let start = self.position;
+        let line = self.line;
+        let column = self.column();
+
+        self.read_char();
 
-        string
+        let decoded = match self.current_char {
+            // The input ended right after the backslash, read_string reports the open string
+            _ if self.at_end() => return,
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            'u' => match self.read_unicode_escape() {
+                Some(ch) => ch,
+                None => {
+                    let sequence = self.input[start..self.read_position].to_owned();
+                    let span = Span::new(start, self.read_position, line, column);
+
+                    value.push_str(&sequence);
+                    self.errors
+                        .push(LexError::InvalidUnicodeEscape { sequence, span });
+                    return;
+                }
+            },
+            _ => {
+                let sequence = self.input[start..self.read_position].to_owned();
+                let span = Span::new(start, self.read_position, line, column);
+
+                value.push_str(&sequence);
+                self.errors.push(LexError::UnknownEscape { sequence, span });
+                return;
+            }
+        };
+
+        value.push(decoded);
     }
 
+    /// read_unicode_escape reads the `{...}` of a `\u{...}` escape, with current_char on the `u`.
+    /// Characters are only consumed while they can still be part of the escape, so whatever ends a
+    /// malformed one is left for read_string
+    fn read_unicode_escape(&mut self) -> Option<char> {
+        if self.peek() != '{' {
+            return None;
+        }
+        self.read_char();
+
+        let digits_start = self.read_position;
+        while self.peek().is_ascii_hexdigit() {
+            self.read_char();
+        }
+        let digits = &self.input[digits_start..self.read_position];
+
+        if self.peek() != '}' {
+            return None;
+        }
+        self.read_char();
+
+        if digits.is_empty() || digits.len() > 6 {
+            return None;
+        }
+
+        u32::from_str_radix(digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+    }
+
+    /// read_identifier reads an identifier, which follows the Unicode XID rules (see the unicode
+    /// module) with `_` also allowed at the start
     fn read_identifier(&mut self) -> String {
         let position = self.position;
 
-        while is_letter(self.current_char) {
+        while unicode::is_xid_continue(self.current_char) {
             self.read_char();
         }
 
-        let string: String = self.input[position..self.position].iter().collect();
-
-        string
+        self.input[position..self.position].to_owned()
     }
 
-    fn read_integer(&mut self) -> String {
+    /// read_number reads an integer, or a float if the digits are followed by a fraction
+    /// (`.` and digits) and/or an exponent (`e` or `E`, an optional sign, and digits). A `.` or
+    /// `e` that isn't followed by a digit is left for the next token. Integers can also be
+    /// written in hexadecimal (`0x`), binary (`0b`) or octal (`0o`), and any group of digits can
+    /// be split up with `_`. The literal is returned as written, the parser works out its value
+    fn read_number(&mut self) -> (TokenType, String) {
         let position = self.position;
 
-        while is_integer(self.current_char) {
+        if self.current_char == '0' {
+            if let Some(radix) = prefix_radix(self.peek()) {
+                return self.read_prefixed_integer(radix);
+            }
+        }
+
+        let mut token_type = TokenType::INTEGER;
+        let mut groups = vec![];
+
+        groups.push(self.read_digits());
+
+        if self.current_char == '.' && is_integer(self.peek()) {
+            token_type = TokenType::FLOAT;
             self.read_char();
+            groups.push(self.read_digits());
+        }
+
+        if self.current_char == 'e' || self.current_char == 'E' {
+            let mut rest = self.input[self.read_position..].chars();
+            let exponent = match rest.next() {
+                Some('+') | Some('-') => rest.next().is_some_and(is_integer),
+                Some(ch) => is_integer(ch),
+                None => false,
+            };
+
+            if exponent {
+                token_type = TokenType::FLOAT;
+                self.read_char();
+                if self.current_char == '+' || self.current_char == '-' {
+                    self.read_char();
+                }
+                groups.push(self.read_digits());
+            }
         }
 
-        let string: String = self.input[position..self.position].iter().collect();
+        for (start, end) in groups {
+            self.check_digits(position, start..end, 10);
+        }
 
-        string
+        (token_type, self.input[position..self.position].to_owned())
     }
 
-    fn skip_whitespace(&mut self) {
-        while self.current_char == ' '
-            || self.current_char == '\t'
-            || self.current_char == '\n'
-            || self.current_char == '\r'
-        {
-            if self.current_char == '\n' {
-                self.line += 1;
-            }
+    /// read_prefixed_integer reads a `0x`, `0b` or `0o` integer, with current_char on the `0`.
+    /// Every letter and digit after the prefix is read, so a stray one like the `g` in `0xfg` is
+    /// reported as part of the literal rather than starting an identifier
+    fn read_prefixed_integer(&mut self, radix: u32) -> (TokenType, String) {
+        let position = self.position;
+
+        self.read_char();
+        self.read_char();
 
+        let digits = self.position;
+        while self.current_char.is_ascii_alphanumeric() || self.current_char == '_' {
             self.read_char();
         }
+
+        if digits == self.position {
+            let prefix = self.input[position..self.position].to_owned();
+            let span = self.span_on_line(position, self.position);
+            self.errors.push(LexError::MissingDigits { prefix, span });
+        } else {
+            self.check_digits(position, digits..self.position, radix);
+        }
+
+        (
+            TokenType::INTEGER,
+            self.input[position..self.position].to_owned(),
+        )
     }
 
-    fn skip_single_line_comment(&mut self) {
-        while self.current_char != '\n' && self.current_char != '\0' {
+    /// read_digits reads decimal digits and `_` separators, returning where they start and end
+    fn read_digits(&mut self) -> (usize, usize) {
+        let start = self.position;
+
+        while is_integer(self.current_char) || self.current_char == '_' {
             self.read_char();
         }
 
-        self.skip_whitespace();
+        (start, self.position)
     }
 
-    fn skip_multi_line_comment(&mut self) {
-        let mut end_found = false;
+    /// check_digits reports any character in the `group` of digits that isn't a digit in `radix`,
+    /// and any run of `_` that isn't a single separator between two digits. Called once the
+    /// whole number, which starts at `literal`, has been read
+    fn check_digits(&mut self, literal: usize, group: Range<usize>, radix: u32) {
+        let digits = &self.input[group.clone()];
+        let mut errors = vec![];
+        let mut offset = 0;
+
+        while offset < digits.len() {
+            let ch = digits[offset..].chars().next().unwrap_or('\0');
 
-        while !end_found {
-            if self.current_char == '\0' {
-                end_found = true;
+            if ch == '_' {
+                let run = digits[offset..].len() - digits[offset..].trim_start_matches('_').len();
+                let between_digits = offset > 0 && offset + run < digits.len();
+
+                if run > 1 || !between_digits {
+                    let literal = self.input[literal..self.position].to_owned();
+                    let span = self.span_on_line(group.start + offset, group.start + offset + run);
+                    errors.push(LexError::MisplacedSeparator { literal, span });
+                }
+
+                offset += run;
+                continue;
             }
 
-            if self.current_char == '*' && self.peek() == '/' {
-                end_found = true;
-                self.read_char();
+            if !ch.is_digit(radix) {
+                let span =
+                    self.span_on_line(group.start + offset, group.start + offset + ch.len_utf8());
+                errors.push(LexError::InvalidDigit {
+                    digit: ch,
+                    radix,
+                    span,
+                });
             }
 
-            self.read_char();
+            offset += ch.len_utf8();
         }
 
-        self.skip_whitespace();
+        self.errors.extend(errors);
     }
 
-    fn peek(&self) -> char {
-        if self.read_position >= self.input.len() {
-            return '\0';
-        }
-
-        return self.input[self.read_position];
+    /// span_on_line returns the Span from `start` to `end`, both on the current line at or
+    /// before current_char
+    fn span_on_line(&self, start: usize, end: usize) -> Span {
+        let column = self.column - self.input[start..self.position].chars().count();
+        Span::new(start, end, self.line, column)
     }
 
-    pub fn next_token(mut self) -> Token {
-        let mut token = Token {
-            token_type: TokenType::NONE,
-            literal: "".to_owned(),
-            line: 0,
-        };
+    fn skip_whitespace(&mut self) {
+        while self.current_char == ' '
+            || self.current_char == '\t'
+            || self.current_char == '\n'
+            || self.current_char == '\r'
+        {
+            self.read_char();
+        }
+    }
 
-        self.skip_whitespace();
+    fn skip_single_line_comment(&mut self) {
+        while self.current_char != '\n' && !self.at_end() {
+            self.read_char();
+        }
+    }
 
-        match self.current_char {
-            '=' => {
-                if self.peek() == '=' {
-                    let ch = self.current_char;
+    /// skip_multi_line_comment skips a `/* */` comment, with current_char on its `/`. Block
+    /// comments nest, so each `/*` inside one needs its own `*/` too. Reaching the end of the
+    /// input first is reported, pointing at the opening `/*`
+    fn skip_multi_line_comment(&mut self) {
+        let opening = Span::new(self.position, self.position + 2, self.line, self.column());
+        let mut depth = 0;
 
+        loop {
+            match (self.current_char, self.peek()) {
+                _ if self.at_end() => {
+                    self.errors
+                        .push(LexError::UnterminatedComment { span: opening });
+                    return;
+                }
+                ('/', '*') => {
+                    depth += 1;
                     self.read_char();
-
-                    token = Token {
-                        token_type: TokenType::EQUAL_EQUAL,
-                        literal: ch.to_string() + &self.current_char.to_string(),
-                        line: self.line,
-                    };
-                } else {
-                    token = new_token(TokenType::EQUAL, self.line, self.current_char.to_string());
                 }
-            }
-            '+' => {
-                if self.peek() == '+' {
-                    let ch = self.current_char;
-
+                ('*', '/') => {
+                    depth -= 1;
                     self.read_char();
 
-                    token = Token {
-                        token_type: TokenType::PLUS_PLUS,
-                        literal: ch.to_string() + &self.current_char.to_string(),
-                        line: self.line,
+                    if depth == 0 {
+                        self.read_char();
+                        return;
                     }
-                } else {
-                    token = new_token(TokenType::PLUS, self.line, self.current_char.to_string());
                 }
+                _ => {}
             }
-            '-' => {
-                if self.peek() == '-' {
-                    let ch = self.current_char;
 
-                    self.read_char();
+            self.read_char();
+        }
+    }
 
-                    token = Token {
-                        token_type: TokenType::MINUS_MINUS,
-                        literal: ch.to_string() + &self.current_char.to_string(),
-                        line: self.line,
-                    }
-                } else {
-                    token = new_token(TokenType::MINUS, self.line, self.current_char.to_string())
-                }
-            }
-            '!' => {
-                if self.peek() == '=' {
-                    let ch = self.current_char;
+    fn peek(&self) -> char {
+        self.input[self.read_position..]
+            .chars()
+            .next()
+            .unwrap_or('\0')
+    }
 
-                    self.read_char();
+    /// at_comment reports whether a `//` or `/*` comment starts at current_char
+    fn at_comment(&self) -> bool {
+        self.current_char == '/' && (self.peek() == '/' || self.peek() == '*')
+    }
 
-                    token = Token {
-                        token_type: TokenType::BANG_EQUAL,
-                        literal: ch.to_string() + &self.current_char.to_string(),
-                        line: self.line,
-                    }
-                } else {
-                    token = new_token(TokenType::BANG, self.line, self.current_char.to_string());
-                }
-            }
-            '*' => {
-                token = new_token(TokenType::STAR, self.line, self.current_char.to_string());
-            }
-            '/' => {
-                if self.peek() == '/' {
-                    self.skip_single_line_comment();
-                    return self.next_token();
-                }
+    /// read_comment skips the comment starting at current_char, records it and returns its span
+    fn read_comment(&mut self) -> Span {
+        let start = self.position;
+        let line = self.line;
+        let column = self.column();
 
-                if self.peek() == '*' {
-                    self.skip_multi_line_comment();
-                    return self.next_token();
-                }
+        if self.peek() == '/' {
+            self.skip_single_line_comment();
+        } else {
+            self.skip_multi_line_comment();
+        }
 
-                token = new_token(TokenType::SLASH, self.line, self.current_char.to_string());
-            }
-            '%' => {
-                token = new_token(TokenType::MOD, self.line, self.current_char.to_string());
-            }
-            '<' => {
-                if self.peek() == '=' {
-                    let ch = self.current_char;
+        let span = Span::new(start, self.position, line, column);
+        let text = self.input[span.start..span.end].trim_end().to_owned();
+        self.comments.push(Comment { text, span });
 
-                    self.read_char();
+        span
+    }
 
-                    let literal = ch.to_string() + &self.current_char.to_string();
+    /// skip_trivia skips the whitespace and comments before the next real token, recording the
+    /// comments. It loops rather than lexing on recursively, so any number of comments in a row
+    /// is fine
+    fn skip_trivia(&mut self) {
+        loop {
+            self.skip_whitespace();
 
-                    token = new_token(TokenType::LESS_EQUAL, self.line, literal);
-                } else {
-                    token = new_token(TokenType::LESS, self.line, self.current_char.to_string());
-                }
+            if !self.at_comment() {
+                return;
             }
-            '>' => {
-                if self.peek() == '=' {
-                    let ch = self.current_char;
+            self.read_comment();
+        }
+    }
 
-                    self.read_char();
+    fn trivia_token(&self, token_type: TokenType, span: Span) -> Token {
+        new_token(
+            token_type,
+            self.input[span.start..span.end].to_owned(),
+            span,
+        )
+    }
 
-                    let literal = ch.to_string() + &self.current_char.to_string();
+    /// take_errors returns the errors found since the last call, leaving none behind. The lexer
+    /// reports problems this way rather than failing, so it always produces a token
+    pub fn take_errors(&mut self) -> Vec<LexError> {
+        std::mem::take(&mut self.errors)
+    }
 
-                    token = new_token(TokenType::GREATER_EQUAL, self.line, literal);
-                } else {
-                    token = new_token(TokenType::GREATER, self.line, self.current_char.to_string());
-                }
-            }
-            '&' => {
-                if self.peek() == '&' {
-                    let ch = self.current_char;
+    /// comments returns every comment the lexer has skipped over so far, in source order
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
 
-                    self.read_char();
+    /// column returns the 1-based column of current_char
+    fn column(&self) -> usize {
+        self.column
+    }
 
-                    let literal = ch.to_string() + &self.current_char.to_string();
+    /// read_operator reads a two character operator (==, ++, etc) if the next character is
+    /// `second`, otherwise the single character operator
+    fn read_operator(
+        &mut self,
+        second: char,
+        double: TokenType,
+        single: TokenType,
+    ) -> (TokenType, String) {
+        if self.peek() == second {
+            let ch = self.current_char;
 
-                    token = new_token(TokenType::AND, self.line, literal);
-                }
-            }
-            '|' => {
-                if self.peek() == '|' {
-                    let ch = self.current_char;
+            self.read_char();
 
-                    self.read_char();
+            return (double, ch.to_string() + &self.current_char.to_string());
+        }
 
-                    let literal = ch.to_string() + &self.current_char.to_string();
+        (single, self.current_char.to_string())
+    }
 
-                    token = new_token(TokenType::OR, self.line, literal);
-                }
-            }
-            ',' => {
-                token = new_token(TokenType::COMMA, self.line, self.current_char.to_string());
-            }
-            ':' => {
-                token = new_token(TokenType::COLON, self.line, self.current_char.to_string());
-            }
-            ';' => {
-                token = new_token(
-                    TokenType::SEMICOLON,
-                    self.line,
-                    self.current_char.to_string(),
-                );
-            }
-            '(' => {
-                token = new_token(
-                    TokenType::LEFT_PAREN,
-                    self.line,
-                    self.current_char.to_string(),
-                );
-            }
-            ')' => {
-                token = new_token(
-                    TokenType::RIGHT_PAREN,
-                    self.line,
-                    self.current_char.to_string(),
-                );
-            }
-            '{' => {
-                token = new_token(
-                    TokenType::LEFT_BRACE,
-                    self.line,
-                    self.current_char.to_string(),
-                );
-            }
-            '}' => {
-                token = new_token(
-                    TokenType::RIGHT_BRACE,
-                    self.line,
-                    self.current_char.to_string(),
-                );
-            }
-            '[' => {
-                token = new_token(
-                    TokenType::LEFT_BRACKET,
-                    self.line,
-                    self.current_char.to_string(),
-                );
-            }
-            ']' => {
-                token = new_token(
-                    TokenType::RIGHT_BRACKET,
-                    self.line,
-                    self.current_char.to_string(),
-                );
+    pub fn next_token(&mut self) -> Token {
+        if !self.trivia {
+            self.skip_trivia();
+        }
+
+        let start = self.position;
+        let line = self.line;
+        let column = self.column();
+
+        let (token_type, literal) = match self.current_char {
+            _ if self.at_end() => (TokenType::EOF, "".to_owned()),
+            ' ' | '\t' | '\n' | '\r' => {
+                // Only reachable in trivia mode, whitespace is skipped above otherwise
+                self.skip_whitespace();
+                let span = Span::new(start, self.position, line, column);
+                return self.trivia_token(TokenType::WHITESPACE, span);
             }
-            '"' => {
-                token.token_type = TokenType::STRING;
-                token.literal = self.read_string();
-                token.line = self.line;
+            '=' => self.read_operator('=', TokenType::EQUAL_EQUAL, TokenType::EQUAL),
+            '+' => self.read_operator('+', TokenType::PLUS_PLUS, TokenType::PLUS),
+            '-' => self.read_operator('-', TokenType::MINUS_MINUS, TokenType::MINUS),
+            '!' => self.read_operator('=', TokenType::BANG_EQUAL, TokenType::BANG),
+            '*' => (TokenType::STAR, self.current_char.to_string()),
+            '/' if self.at_comment() => {
+                // Only reachable in trivia mode, comments are skipped above otherwise
+                let span = self.read_comment();
+                return self.trivia_token(TokenType::COMMENT, span);
             }
-            '\0' => {
-                token.literal = "".to_owned();
-                token.token_type = TokenType::EOF;
-                token.line = self.line;
+            '/' => (TokenType::SLASH, self.current_char.to_string()),
+            '%' => (TokenType::MOD, self.current_char.to_string()),
+            '<' => self.read_operator('=', TokenType::LESS_EQUAL, TokenType::LESS),
+            '>' => self.read_operator('=', TokenType::GREATER_EQUAL, TokenType::GREATER),
+            '&' | '|' if self.peek() != self.current_char => {
+                let span = Span::new(start, self.read_position, line, column);
+                self.errors.push(LexError::LoneOperator {
+                    operator: self.current_char,
+                    span,
+                });
+                (TokenType::ILLEGAL, self.current_char.to_string())
             }
+            '&' => self.read_operator('&', TokenType::AND, TokenType::ILLEGAL),
+            '|' => self.read_operator('|', TokenType::OR, TokenType::ILLEGAL),
+            ',' => (TokenType::COMMA, self.current_char.to_string()),
+            ':' => (TokenType::COLON, self.current_char.to_string()),
+            ';' => (TokenType::SEMICOLON, self.current_char.to_string()),
+            '(' => (TokenType::LEFT_PAREN, self.current_char.to_string()),
+            ')' => (TokenType::RIGHT_PAREN, self.current_char.to_string()),
+            '{' => (TokenType::LEFT_BRACE, self.current_char.to_string()),
+            '}' => (TokenType::RIGHT_BRACE, self.current_char.to_string()),
+            '[' => (TokenType::LEFT_BRACKET, self.current_char.to_string()),
+            ']' => (TokenType::RIGHT_BRACKET, self.current_char.to_string()),
+            '"' => (TokenType::STRING, self.read_string()),
             _ => {
-                if is_letter(self.current_char) {
-                    token.literal = self.read_identifier();
-                    token.token_type = look_up_identifier(&token.literal);
-                    token.line = self.line;
+                if is_identifier_start(self.current_char) {
+                    let literal = self.read_identifier();
+                    let token_type = look_up_identifier(&literal);
+                    // read_identifier has already advanced past the identifier
+                    return new_token(
+                        token_type,
+                        literal,
+                        Span::new(start, self.position, line, column),
+                    );
                 } else if is_integer(self.current_char) {
-                    token.literal = self.read_integer();
-                    token.token_type = TokenType::INTEGER;
-                    token.line = self.line;
-                } else {
-                    token = new_token(TokenType::ILLEGAL, self.line, self.current_char.to_string())
+                    let (token_type, literal) = self.read_number();
+                    // read_number has already advanced past the number
+                    return new_token(
+                        token_type,
+                        literal,
+                        Span::new(start, self.position, line, column),
+                    );
                 }
+
+                let span = Span::new(start, self.read_position, line, column);
+                self.errors.push(LexError::UnexpectedCharacter {
+                    character: self.current_char,
+                    span,
+                });
+                (TokenType::ILLEGAL, self.current_char.to_string())
             }
-        }
+        };
 
         self.read_char();
 
-        token
+        new_token(
+            token_type,
+            literal,
+            Span::new(start, self.position, line, column),
+        )
+    }
+}
+
+/// Lexer yields every token up to, but not including, the EOF token. Once EOF is reached it keeps
+/// returning None, so the iterator is fused.
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.next_token();
+
+        if token.token_type == TokenType::EOF {
+            return None;
+        }
+
+        Some(token)
     }
 }
 
-fn new_token(token_type: TokenType, line: usize, literal: String) -> Token {
+impl<'a> FusedIterator for Lexer<'a> {}
+
+/// tokenize lexes the whole input and returns its tokens, excluding the trailing EOF token
+pub fn tokenize(input: &str) -> Vec<Token> {
+    Lexer::new(input).collect()
+}
+
+fn new_token(token_type: TokenType, literal: String, span: Span) -> Token {
     Token {
         token_type,
         literal,
-        line,
+        span,
     }
 }
 
-fn is_letter(character: char) -> bool {
-    'a' <= character && character <= 'z' || 'A' <= character && character <= 'Z' || character == '_'
+fn is_identifier_start(character: char) -> bool {
+    unicode::is_xid_start(character) || character == '_'
 }
 
 fn is_integer(character: char) -> bool {
-    '0' <= character && character <= '9'
+    character.is_ascii_digit()
+}
+
+/// prefix_radix returns the radix an integer prefix (the letter after its `0`) stands for
+fn prefix_radix(character: char) -> Option<u32> {
+    match character {
+        'x' => Some(16),
+        'b' => Some(2),
+        'o' => Some(8),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_tools::parser::Parser;
+
+    /// lex returns every token of `source` before EOF, and the errors found lexing it
+    fn lex(source: &str) -> (Vec<Token>, Vec<LexError>) {
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.by_ref().collect();
+
+        (tokens, lexer.take_errors())
+    }
+
+    fn token_types(tokens: &[Token]) -> Vec<TokenType> {
+        tokens.iter().map(|token| token.token_type).collect()
+    }
+
+    #[test]
+    fn iterator_is_fused_after_eof() {
+        let mut lexer = Lexer::new("x");
+
+        assert_eq!(
+            lexer.next().map(|token| token.literal),
+            Some("x".to_owned())
+        );
+        for _ in 0..3 {
+            assert_eq!(lexer.next(), None);
+        }
+        assert_eq!(lexer.next_token().token_type, TokenType::EOF);
+    }
+
+    #[test]
+    fn tokenize_excludes_eof() {
+        let tokens = tokenize("let five = 5;\nfive + 1.5");
+
+        assert_eq!(
+            token_types(&tokens),
+            [
+                TokenType::LET,
+                TokenType::IDENTIFIER,
+                TokenType::EQUAL,
+                TokenType::INTEGER,
+                TokenType::SEMICOLON,
+                TokenType::IDENTIFIER,
+                TokenType::PLUS,
+                TokenType::FLOAT,
+            ]
+        );
+        let literals: Vec<&str> = tokens.iter().map(|token| &token.literal[..]).collect();
+        assert_eq!(literals, ["let", "five", "=", "5", ";", "five", "+", "1.5"]);
+
+        assert!(tokenize("").is_empty());
+        assert!(tokenize("  // only a comment\n").is_empty());
+    }
+
+    #[test]
+    fn many_comments_in_a_row() {
+        // Comments used to be skipped by lexing on recursively, one stack frame each
+        let source = "// c\n".repeat(200_000) + "x";
+
+        let tokens = tokenize(&source);
+        assert_eq!(token_types(&tokens), [TokenType::IDENTIFIER]);
+
+        let mut parser = Parser::new(Lexer::new(&source));
+        let program = parser.parse_program();
+        assert!(parser.errors().is_empty());
+        assert_eq!(program.statements.len(), 1);
+        assert_eq!(parser.comments().len(), 200_000);
+    }
+
+    #[test]
+    fn string_escapes_are_decoded() {
+        let (tokens, errors) = lex(r#""\n\t\r\0\\\"\u{41}\u{1F600}""#);
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(tokens[0].literal, "\n\t\r\0\\\"A\u{1F600}");
+    }
+
+    #[test]
+    fn unknown_escape() {
+        let (tokens, errors) = lex(r#""a\qb""#);
+
+        assert_eq!(
+            errors,
+            [LexError::UnknownEscape {
+                sequence: r"\q".to_owned(),
+                span: Span::new(2, 4, 1, 3),
+            }]
+        );
+        // The escape is kept as written
+        assert_eq!(tokens[0].literal, r"a\qb");
+    }
+
+    #[test]
+    fn invalid_unicode_escapes() {
+        let tests = [
+            // Not a Unicode scalar value
+            (r#""\u{110000}""#, r"\u{110000}", 11),
+            (r#""\u{D800}""#, r"\u{D800}", 9),
+            // No digits, or too many
+            (r#""\u{}""#, r"\u{}", 5),
+            (r#""\u{0000041}""#, r"\u{0000041}", 12),
+            // Not hex: the escape stops at the `{`, and the rest of the string is kept as is
+            (r#""\u{zz}""#, r"\u{", 4),
+            // No braces
+            (r#""\u0041""#, r"\u", 3),
+        ];
+
+        for (source, sequence, end) in tests.iter() {
+            let (tokens, errors) = lex(source);
+
+            assert_eq!(
+                errors,
+                [LexError::InvalidUnicodeEscape {
+                    sequence: sequence.to_string(),
+                    span: Span::new(1, *end, 1, 2),
+                }],
+                "{}",
+                source
+            );
+            assert_eq!(tokens[0].literal, source[1..source.len() - 1], "{}", source);
+        }
+    }
+
+    #[test]
+    fn unterminated_strings() {
+        let (tokens, errors) = lex("let s = \"abc");
+        assert_eq!(
+            errors,
+            [LexError::UnterminatedString {
+                span: Span::new(8, 9, 1, 9),
+            }]
+        );
+        assert_eq!(tokens[3].token_type, TokenType::STRING);
+        assert_eq!(tokens[3].literal, "abc");
+
+        // Ending right after a backslash is still only the one error
+        let (_, errors) = lex("\"a\\");
+        assert_eq!(
+            errors,
+            [LexError::UnterminatedString {
+                span: Span::new(0, 1, 1, 1),
+            }]
+        );
+
+        // Strings may span lines, so the error points back at the opening quote
+        let (_, errors) = lex("x\n  \"a\nb");
+        assert_eq!(
+            errors,
+            [LexError::UnterminatedString {
+                span: Span::new(4, 5, 2, 3),
+            }]
+        );
+    }
+
+    #[test]
+    fn prefixed_and_separated_integers() {
+        let (tokens, errors) = lex("0xff_FF 0b1010 0o17 1_000 1_0.2_5e1_0");
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        let literals: Vec<(TokenType, &str)> = tokens
+            .iter()
+            .map(|token| (token.token_type, &token.literal[..]))
+            .collect();
+        assert_eq!(
+            literals,
+            [
+                (TokenType::INTEGER, "0xff_FF"),
+                (TokenType::INTEGER, "0b1010"),
+                (TokenType::INTEGER, "0o17"),
+                (TokenType::INTEGER, "1_000"),
+                (TokenType::FLOAT, "1_0.2_5e1_0"),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_digits() {
+        let (tokens, errors) = lex("0x;");
+
+        assert_eq!(
+            errors,
+            [LexError::MissingDigits {
+                prefix: "0x".to_owned(),
+                span: Span::new(0, 2, 1, 1),
+            }]
+        );
+        assert_eq!(
+            token_types(&tokens),
+            [TokenType::INTEGER, TokenType::SEMICOLON]
+        );
+    }
+
+    #[test]
+    fn invalid_digits() {
+        let (_, errors) = lex("0b102");
+        assert_eq!(
+            errors,
+            [LexError::InvalidDigit {
+                digit: '2',
+                radix: 2,
+                span: Span::new(4, 5, 1, 5),
+            }]
+        );
+
+        // Every bad digit is reported, and letters after the prefix belong to the literal
+        let (tokens, errors) = lex("x = 0o8g9");
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(
+            errors,
+            [
+                LexError::InvalidDigit {
+                    digit: '8',
+                    radix: 8,
+                    span: Span::new(6, 7, 1, 7),
+                },
+                LexError::InvalidDigit {
+                    digit: 'g',
+                    radix: 8,
+                    span: Span::new(7, 8, 1, 8),
+                },
+                LexError::InvalidDigit {
+                    digit: '9',
+                    radix: 8,
+                    span: Span::new(8, 9, 1, 9),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn misplaced_separators() {
+        let tests = [
+            ("1__0", Span::new(1, 3, 1, 2)),
+            ("1_", Span::new(1, 2, 1, 2)),
+            ("0x_ff", Span::new(2, 3, 1, 3)),
+            ("1_.5", Span::new(1, 2, 1, 2)),
+            ("1.5_e3", Span::new(3, 4, 1, 4)),
+        ];
+
+        for (source, span) in tests.iter() {
+            let (_, errors) = lex(source);
+
+            assert_eq!(
+                errors,
+                [LexError::MisplacedSeparator {
+                    literal: source.to_string(),
+                    span: *span,
+                }],
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn unexpected_characters() {
+        let (tokens, errors) = lex("a @ b");
+        assert_eq!(
+            token_types(&tokens),
+            [
+                TokenType::IDENTIFIER,
+                TokenType::ILLEGAL,
+                TokenType::IDENTIFIER
+            ]
+        );
+        assert_eq!(
+            errors,
+            [LexError::UnexpectedCharacter {
+                character: '@',
+                span: Span::new(2, 3, 1, 3),
+            }]
+        );
+
+        // Spans cover the whole character, and columns count characters rather than bytes
+        let (_, errors) = lex("é € 'x'");
+        assert_eq!(
+            errors,
+            [
+                LexError::UnexpectedCharacter {
+                    character: '€',
+                    span: Span::new(3, 6, 1, 3),
+                },
+                LexError::UnexpectedCharacter {
+                    character: '\'',
+                    span: Span::new(7, 8, 1, 5),
+                },
+                LexError::UnexpectedCharacter {
+                    character: '\'',
+                    span: Span::new(9, 10, 1, 7),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        let (tokens, errors) = lex("let größe = 変数 + नमस्ते;");
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        // Columns count characters: the viramas and vowel signs in नमस्ते are characters too
+        let identifiers: Vec<(&str, Span)> = tokens
+            .iter()
+            .filter(|token| token.token_type == TokenType::IDENTIFIER)
+            .map(|token| (token.literal.as_str(), token.span))
+            .collect();
+        assert_eq!(
+            identifiers,
+            [
+                ("größe", Span::new(4, 11, 1, 5)),
+                ("変数", Span::new(14, 20, 1, 13)),
+                ("नमस्ते", Span::new(23, 41, 1, 18)),
+            ]
+        );
+        assert_eq!(tokens.last().unwrap().span, Span::new(41, 42, 1, 24));
+    }
+
+    #[test]
+    fn identifiers_start_with_a_letter() {
+        // A combining mark can continue an identifier, but not begin one
+        let (tokens, errors) = lex("\u{093E}x");
+        assert_eq!(
+            token_types(&tokens),
+            [TokenType::ILLEGAL, TokenType::IDENTIFIER]
+        );
+        assert_eq!(tokens[1].span, Span::new(3, 4, 1, 2));
+        assert_eq!(
+            errors,
+            [LexError::UnexpectedCharacter {
+                character: '\u{093E}',
+                span: Span::new(0, 3, 1, 1),
+            }]
+        );
+
+        // Superscript digits are numbers, but not decimal digits, so they don't continue one
+        let (tokens, errors) = lex("x²");
+        assert_eq!(
+            token_types(&tokens),
+            [TokenType::IDENTIFIER, TokenType::ILLEGAL]
+        );
+        assert_eq!(tokens[0].literal, "x");
+        assert_eq!(
+            errors,
+            [LexError::UnexpectedCharacter {
+                character: '²',
+                span: Span::new(1, 3, 1, 2),
+            }]
+        );
+    }
+
+    #[test]
+    fn lone_operators() {
+        let (tokens, errors) = lex("a & b | c");
+        assert_eq!(tokens[1].token_type, TokenType::ILLEGAL);
+        assert_eq!(tokens[3].token_type, TokenType::ILLEGAL);
+        assert_eq!(
+            errors,
+            [
+                LexError::LoneOperator {
+                    operator: '&',
+                    span: Span::new(2, 3, 1, 3),
+                },
+                LexError::LoneOperator {
+                    operator: '|',
+                    span: Span::new(6, 7, 1, 7),
+                },
+            ]
+        );
+
+        let (tokens, errors) = lex("a &&& b");
+        assert_eq!(
+            token_types(&tokens),
+            [
+                TokenType::IDENTIFIER,
+                TokenType::AND,
+                TokenType::ILLEGAL,
+                TokenType::IDENTIFIER
+            ]
+        );
+        assert_eq!(
+            errors,
+            [LexError::LoneOperator {
+                operator: '&',
+                span: Span::new(4, 5, 1, 5),
+            }]
+        );
+
+        let (_, errors) = lex("a && b || c");
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        let source = "/* a /* b */ c */ x /**/";
+        let mut lexer = Lexer::new(source);
+        let tokens: Vec<Token> = lexer.by_ref().collect();
+
+        assert!(lexer.take_errors().is_empty());
+        assert_eq!(token_types(&tokens), [TokenType::IDENTIFIER]);
+        assert_eq!(
+            lexer.comments(),
+            [
+                Comment {
+                    text: "/* a /* b */ c */".to_owned(),
+                    span: Span::new(0, 17, 1, 1),
+                },
+                Comment {
+                    text: "/**/".to_owned(),
+                    span: Span::new(20, 24, 1, 21),
+                },
+            ]
+        );
+
+        // A `*/` outside a comment is just a multiplication and a division
+        let (tokens, _) = lex("*/");
+        assert_eq!(token_types(&tokens), [TokenType::STAR, TokenType::SLASH]);
+    }
+
+    #[test]
+    fn unterminated_block_comments() {
+        let (tokens, errors) = lex("x /* a\nb");
+        assert_eq!(token_types(&tokens), [TokenType::IDENTIFIER]);
+        assert_eq!(
+            errors,
+            [LexError::UnterminatedComment {
+                span: Span::new(2, 4, 1, 3),
+            }]
+        );
+
+        // With a nested comment closed but not the outer one, the error is about the outer `/*`
+        let (_, errors) = lex("/* a /* b */ c");
+        assert_eq!(
+            errors,
+            [LexError::UnterminatedComment {
+                span: Span::new(0, 2, 1, 1),
+            }]
+        );
+    }
+
+    #[test]
+    fn nul_is_an_unexpected_character_not_the_end() {
+        let (tokens, errors) = lex("let a = \0; a");
+
+        assert_eq!(
+            token_types(&tokens),
+            [
+                TokenType::LET,
+                TokenType::IDENTIFIER,
+                TokenType::EQUAL,
+                TokenType::ILLEGAL,
+                TokenType::SEMICOLON,
+                TokenType::IDENTIFIER,
+            ]
+        );
+        assert_eq!(
+            errors,
+            [LexError::UnexpectedCharacter {
+                character: '\0',
+                span: Span::new(8, 9, 1, 9),
+            }]
+        );
+    }
+
+    #[test]
+    fn nul_is_kept_inside_strings() {
+        let (tokens, errors) = lex("\"x\0y\" + z");
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(
+            token_types(&tokens),
+            [TokenType::STRING, TokenType::PLUS, TokenType::IDENTIFIER]
+        );
+        assert_eq!(tokens[0].literal, "x\0y");
+        assert_eq!(tokens[0].span, Span::new(0, 5, 1, 1));
+    }
+
+    #[test]
+    fn nul_does_not_end_a_comment() {
+        let (tokens, errors) = lex("// a \0 b\n/* c \0 d */ e");
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(token_types(&tokens), [TokenType::IDENTIFIER]);
+        assert_eq!(tokens[0].literal, "e");
+    }
 }
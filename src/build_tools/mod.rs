@@ -1,4 +1,12 @@
 pub mod ast;
+pub mod cst;
+pub mod diagnostics;
+pub mod dump;
+pub mod error;
+pub mod fold;
+pub mod formatter;
 pub mod lexer;
 pub mod parser;
 pub mod token;
+pub mod unicode;
+pub mod visit;
@@ -1,99 +1,113 @@
-use crate::build_tools::ast;
+use crate::build_tools::ast::{self, Node};
+use crate::build_tools::error::ParseError;
 use crate::build_tools::lexer::Lexer;
 use crate::build_tools::token::*;
 
 use std::collections::HashMap;
+use std::rc::Rc;
 
 /// Operator precedence constants
 static LOWEST: usize = 1;
-/// =
-static EQUALS: usize = 2;
-/// && and ||
-static LOGICAL: usize = 3;
+/// ||
+static LOGICAL_OR: usize = 2;
+/// &&
+static LOGICAL_AND: usize = 3;
+/// == or !=
+static EQUALS: usize = 4;
 /// > or <
-static LESS_GREATER: usize = 4;
+static LESS_GREATER: usize = 5;
 /// +
-static SUM: usize = 5;
+static SUM: usize = 6;
 /// *
-static PRODUCT: usize = 6;
+static PRODUCT: usize = 7;
 /// %
-static MODULO: usize = 7;
+static MODULO: usize = 8;
 /// -x or !x
-static PREFIX: usize = 8;
+pub(crate) static PREFIX: usize = 9;
 /// myFunction(x)
-static CALL: usize = 9;
+pub(crate) static CALL: usize = 10;
 /// array[index], hash[key]
-static INDEX: usize = 10;
-
-struct Precedences;
-
-/// Operator precedence table
-impl Precedences {
-    pub fn all() -> HashMap<TokenType, usize> {
-        let mut precendences: HashMap<TokenType, usize> = HashMap::new();
-
-        precendences.insert(TokenType::EQUAL_EQUAL, EQUALS);
-        precendences.insert(TokenType::BANG_EQUAL, EQUALS);
-        precendences.insert(TokenType::LESS, LESS_GREATER);
-        precendences.insert(TokenType::GREATER, LESS_GREATER);
-        precendences.insert(TokenType::LESS_EQUAL, LESS_GREATER);
-        precendences.insert(TokenType::GREATER_EQUAL, LESS_GREATER);
-        precendences.insert(TokenType::PLUS, SUM);
-        precendences.insert(TokenType::MINUS, SUM);
-        precendences.insert(TokenType::SLASH, PRODUCT);
-        precendences.insert(TokenType::STAR, PRODUCT);
-        precendences.insert(TokenType::MOD, MODULO);
-        precendences.insert(TokenType::AND, LOGICAL);
-        precendences.insert(TokenType::OR, LOGICAL);
-        precendences.insert(TokenType::LEFT_PAREN, CALL);
-        precendences.insert(TokenType::LEFT_BRACKET, INDEX);
-
-        precendences
+static INDEX: usize = 11;
+
+/// precedence returns how tightly the infix operator `token_type` binds, or LOWEST if it isn't one.
+/// This is the operator precedence table
+pub(crate) fn precedence(token_type: TokenType) -> usize {
+    match token_type {
+        TokenType::EQUAL_EQUAL | TokenType::BANG_EQUAL => EQUALS,
+        TokenType::LESS | TokenType::GREATER | TokenType::LESS_EQUAL | TokenType::GREATER_EQUAL => {
+            LESS_GREATER
+        }
+        TokenType::PLUS | TokenType::MINUS => SUM,
+        TokenType::SLASH | TokenType::STAR => PRODUCT,
+        TokenType::MOD => MODULO,
+        TokenType::AND => LOGICAL_AND,
+        TokenType::OR => LOGICAL_OR,
+        TokenType::LEFT_PAREN => CALL,
+        TokenType::LEFT_BRACKET => INDEX,
+        _ => LOWEST,
     }
 }
 
-type PrefixParseFunc = fn(parser: &mut Parser) -> Box<dyn ast::Expression>;
+/// Expressions and blocks can't be nested deeper than this. Parsing recurses once per level, and
+/// so does everything that walks the AST afterwards, including dropping it, so without a limit a
+/// deeply nested or very long expression would overflow the native stack. An operator applied in
+/// a chain like `a + b + c` counts as a level too, since each one nests the chain so far inside it.
+/// A level takes up to about 11 KiB of stack in a debug build, so the limit fits in the 2 MiB that
+/// spawned threads (test threads included) get by default
+const MAX_NESTING_DEPTH: usize = 128;
+
+/// ParseResult - parse functions either produce a node, or the error that stopped them
+type ParseResult<T> = Result<T, ParseError>;
+
+type PrefixParseFunc = fn(parser: &mut Parser) -> ParseResult<ast::Expression>;
 type InfixParseFunc =
-    fn(parser: &mut Parser, expr: Box<dyn ast::Expression>) -> Box<dyn ast::Expression>;
-type PostfixParseFunc = fn(parser: &mut Parser) -> Box<dyn ast::Expression>;
+    fn(parser: &mut Parser, expr: ast::Expression) -> ParseResult<ast::Expression>;
+type PostfixParseFunc = fn(parser: &mut Parser) -> ParseResult<ast::Expression>;
 
 /// Parser holds a Lexer, its errors, the current_token, peek_token (next token), and
 /// prev_token (used for ++ and --), as well as the prefix/infix/postfix functions
-pub struct Parser {
-    lexer: Lexer,
-    errors: Vec<String>,
+pub struct Parser<'a> {
+    lexer: Lexer<'a>,
+    errors: Vec<ParseError>,
 
     current_token: Token,
     peek_token: Token,
     prev_token: Token,
 
+    /// How many blocks deep the parser currently is, used when recovering from errors
+    block_depth: usize,
+    /// How many expressions and blocks deep the parser currently is, limited to MAX_NESTING_DEPTH
+    nesting_depth: usize,
+
     prefix_parse_funcs: HashMap<TokenType, PrefixParseFunc>,
     infix_parse_funcs: HashMap<TokenType, InfixParseFunc>,
     postfix_parse_funcs: HashMap<TokenType, PostfixParseFunc>,
 }
 
-impl Parser {
+impl<'a> Parser<'a> {
     /// New takes a Lexer, creates a Parser with that Lexer, sets the
     /// current and peek tokens, and returns the Parser.
-    pub fn new(lexer: Lexer) -> Parser {
+    pub fn new(lexer: Lexer<'a>) -> Parser<'a> {
         let mut parser = Parser {
             lexer,
             errors: vec![],
             current_token: Token {
-                line: 0,
+                span: Span::default(),
                 literal: "".to_owned(),
                 token_type: TokenType::NONE,
             },
             peek_token: Token {
-                line: 0,
+                span: Span::default(),
                 literal: "".to_owned(),
                 token_type: TokenType::NONE,
             },
             prev_token: Token {
-                line: 0,
+                span: Span::default(),
                 literal: "".to_owned(),
                 token_type: TokenType::NONE,
             },
+            block_depth: 0,
+            nesting_depth: 0,
             prefix_parse_funcs: HashMap::new(),
             infix_parse_funcs: HashMap::new(),
             postfix_parse_funcs: HashMap::new(),
@@ -102,6 +116,7 @@ impl Parser {
         // Register all of our prefix parse funcs
         parser.register_prefix(TokenType::IDENTIFIER, parse_identifier);
         parser.register_prefix(TokenType::INTEGER, parse_integer_literal);
+        parser.register_prefix(TokenType::FLOAT, parse_float_literal);
         parser.register_prefix(TokenType::BANG, parse_prefix_expr);
         parser.register_prefix(TokenType::MINUS, parse_prefix_expr);
         parser.register_prefix(TokenType::TRUE, parse_boolean);
@@ -115,23 +130,114 @@ impl Parser {
 
         // Register all of our infix parse funcs
         parser.register_infix(TokenType::PLUS, parse_infix_expr);
-        // p.registerInfix(token.Minus, p.parseInfixExpression)
-        // p.registerInfix(token.Slash, p.parseInfixExpression)
-        // p.registerInfix(token.Star, p.parseInfixExpression)
-        // p.registerInfix(token.Mod, p.parseInfixExpression)
-        // p.registerInfix(token.EqualEqual, p.parseInfixExpression)
-        // p.registerInfix(token.BangEqual, p.parseInfixExpression)
-        // p.registerInfix(token.Less, p.parseInfixExpression)
-        // p.registerInfix(token.Greater, p.parseInfixExpression)
-        // p.registerInfix(token.LessEqual, p.parseInfixExpression)
-        // p.registerInfix(token.GreaterEqual, p.parseInfixExpression)
-        // p.registerInfix(token.LeftParen, p.parseCallExpression)
-        // p.registerInfix(token.LeftBracket, p.parseIndexExpr)
-        // p.registerInfix(token.And, p.parseInfixExpression)
-        // p.registerInfix(token.Or, p.parseInfixExpression)
+        parser.register_infix(TokenType::MINUS, parse_infix_expr);
+        parser.register_infix(TokenType::SLASH, parse_infix_expr);
+        parser.register_infix(TokenType::STAR, parse_infix_expr);
+        parser.register_infix(TokenType::MOD, parse_infix_expr);
+        parser.register_infix(TokenType::EQUAL_EQUAL, parse_infix_expr);
+        parser.register_infix(TokenType::BANG_EQUAL, parse_infix_expr);
+        parser.register_infix(TokenType::LESS, parse_infix_expr);
+        parser.register_infix(TokenType::GREATER, parse_infix_expr);
+        parser.register_infix(TokenType::LESS_EQUAL, parse_infix_expr);
+        parser.register_infix(TokenType::GREATER_EQUAL, parse_infix_expr);
+        parser.register_infix(TokenType::LEFT_PAREN, parse_call_expr);
+        parser.register_infix(TokenType::LEFT_BRACKET, parse_index_expr);
+        parser.register_infix(TokenType::AND, parse_infix_expr);
+        parser.register_infix(TokenType::OR, parse_infix_expr);
+
+        // Register all of our postfix parse funcs
+        parser.register_postfix(TokenType::PLUS_PLUS, parse_postfix_expr);
+        parser.register_postfix(TokenType::MINUS_MINUS, parse_postfix_expr);
+
+        // Read two tokens, so current_token and peek_token are both set
+        parser.next_token();
+        parser.next_token();
+
         parser
     }
 
+    /// parse_program parses statements until it reaches EOF and returns the RootNode of the AST.
+    /// Any errors encountered along the way are available through errors().
+    pub fn parse_program(&mut self) -> ast::RootNode {
+        let mut program = ast::RootNode { statements: vec![] };
+
+        while !self.current_token_type_is(TokenType::EOF) {
+            let stmt = self.parse_stmt();
+            let stopped_short = self.stopped_short(&stmt);
+
+            program.statements.push(stmt);
+
+            if !stopped_short {
+                self.next_token();
+            }
+        }
+
+        program
+    }
+
+    /// comments returns every comment in the input the parser has read so far. After parse_program
+    /// that is every comment in the program
+    pub fn comments(&self) -> &[Comment] {
+        self.lexer.comments()
+    }
+
+    /// lexer_reported reports whether the lexer found a problem within `span`
+    fn lexer_reported(&self, span: Span) -> bool {
+        self.errors.iter().any(|error| {
+            matches!(error, ParseError::Lex(_))
+                && error.span().start >= span.start
+                && error.span().end <= span.end
+        })
+    }
+
+    /// reported_by_lexer reports whether `error` is about an ILLEGAL token the lexer has already
+    /// reported, in which case saying the token was unexpected too would add nothing
+    fn reported_by_lexer(&self, error: &ParseError) -> bool {
+        match error {
+            ParseError::MissingPrefixFunction {
+                found: TokenType::ILLEGAL,
+                span,
+                ..
+            }
+            | ParseError::UnexpectedToken {
+                found: TokenType::ILLEGAL,
+                span,
+                ..
+            } => self.lexer_reported(*span),
+            _ => false,
+        }
+    }
+
+    /// doc_comment returns the `///` doc comment for the current token: the run of doc comment
+    /// lines directly before it, with nothing but whitespace in between. Used for let and const
+    fn doc_comment(&self) -> Option<String> {
+        let start = self.prev_token.span.end;
+        let end = self.current_token.span.start;
+
+        // Comments are in source order, so the ones before the token can be found without
+        // scanning every comment in the program
+        let comments = self.comments();
+        let before = comments.partition_point(|comment| comment.span.end <= end);
+
+        let lines: Vec<&str> = comments[..before]
+            .iter()
+            .rev()
+            .take_while(|comment| comment.span.start >= start)
+            .map_while(|comment| comment.doc())
+            .collect();
+
+        if lines.is_empty() {
+            return None;
+        }
+
+        Some(lines.into_iter().rev().collect::<Vec<_>>().join("\n"))
+    }
+
+    /// errors returns every error the parser has collected so far
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
     fn register_prefix(&mut self, token_type: TokenType, func: PrefixParseFunc) {
         self.prefix_parse_funcs.insert(token_type, func);
     }
@@ -140,22 +246,41 @@ impl Parser {
         self.infix_parse_funcs.insert(token_type, func);
     }
 
+    fn register_postfix(&mut self, token_type: TokenType, func: PostfixParseFunc) {
+        self.postfix_parse_funcs.insert(token_type, func);
+    }
+
     fn next_token(&mut self) {
         self.prev_token = self.current_token.clone();
         self.current_token = self.peek_token.clone();
-        self.peek_token = self.lexer.clone().next_token();
+        self.peek_token = self.lexer.next_token();
+
+        // Lexing the new peek token may have gone wrong, report that before the parser gets to
+        // the token and reports anything else about it
+        let lex_errors = self.lexer.take_errors();
+        self.errors
+            .extend(lex_errors.into_iter().map(ParseError::Lex));
     }
 
-    fn parse_expr(&mut self, precedence: usize) -> Option<Box<dyn ast::Expression>> {
+    /// parse_expr parses an expression. Every nested expression is parsed through here, so this and
+    /// parse_block_stmt are where nesting is limited to MAX_NESTING_DEPTH
+    fn parse_expr(&mut self, precedence: usize) -> ParseResult<ast::Expression> {
+        let nesting_depth = self.nesting_depth;
+        let result = self.parse_nested_expr(precedence);
+        self.nesting_depth = nesting_depth;
+
+        result
+    }
+
+    fn parse_nested_expr(&mut self, precedence: usize) -> ParseResult<ast::Expression> {
+        self.nest(0)?;
+
         let prefix = match self.prefix_parse_funcs.get(&self.current_token.token_type) {
             Some(&func) => func,
-            _ => {
-                self.no_prefix_parse_func_error(self.current_token.clone());
-                return None;
-            }
+            _ => return Err(self.no_prefix_parse_func_error()),
         };
 
-        let mut left_expr = prefix(self);
+        let mut left_expr = prefix(self)?;
 
         while !self.peek_token_type_is(TokenType::SEMICOLON)
             && precedence < self.peek_token_precedence()
@@ -163,140 +288,249 @@ impl Parser {
             let infix = match self.infix_parse_funcs.get(&self.peek_token.token_type) {
                 Some(&func) => func,
                 _ => {
-                    return Some(left_expr);
+                    return Ok(left_expr);
                 }
             };
             self.next_token();
-            left_expr = infix(self, left_expr);
+            self.nest(0)?;
+            left_expr = infix(self, left_expr)?;
         }
 
-        Some(left_expr)
+        Ok(left_expr)
+    }
+
+    /// nest goes one level deeper into the expression or block being parsed, failing at the current
+    /// token once that would exceed MAX_NESTING_DEPTH or leave fewer than `room` levels for what is
+    /// nested inside it
+    fn nest(&mut self, room: usize) -> ParseResult<()> {
+        if self.nesting_depth + room >= MAX_NESTING_DEPTH {
+            return Err(ParseError::NestingTooDeep {
+                limit: MAX_NESTING_DEPTH,
+                span: self.current_token.span,
+            });
+        }
+
+        self.nesting_depth += 1;
+
+        Ok(())
     }
 
-    fn parse_block_stmt(&mut self) -> ast::BlockStatement {
+    fn parse_block_stmt(&mut self) -> ParseResult<ast::BlockStatement> {
+        // A block without room for its statements fails as a whole. Otherwise each statement in it
+        // would be too deep on its own and reported separately
+        let nesting_depth = self.nesting_depth;
+        self.nest(1)?;
+
         let mut block = ast::BlockStatement {
             token: self.current_token.clone(),
+            span: self.current_token.span,
             statements: vec![],
         };
 
+        self.block_depth += 1;
         self.next_token();
 
         while !self.current_token_type_is(TokenType::RIGHT_BRACE)
             && !self.current_token_type_is(TokenType::EOF)
         {
-            let stmt = match self.parse_stmt() {
-                Some(stmt) => {
-                    block.statements.push(stmt);
-                }
-                _ => {
-                    return ast::BlockStatement {
-                        token: Token {
-                            line: 0,
-                            literal: "".to_owned(),
-                            token_type: TokenType::NONE,
-                        },
-                        statements: vec![],
-                    }
-                }
-            };
+            let stmt = self.parse_stmt();
+            // A statement cut short by this block's own closing brace (e.g. `{ let x = }`) leaves
+            // the parser sitting on that brace, so the block ends here. Only a brace the error was
+            // found at counts: synchronizing can also stop on the `}` of a nested block it skipped
+            let closed = matches!(stmt, ast::Statement::Error(_))
+                && matches!(
+                    self.errors.last(),
+                    Some(ParseError::MissingPrefixFunction {
+                        found: TokenType::RIGHT_BRACE,
+                        span,
+                        ..
+                    }) if *span == self.current_token.span
+                );
+            let stopped_short = self.stopped_short(&stmt);
 
-            self.next_token();
+            block.statements.push(stmt);
+
+            if closed {
+                break;
+            }
+
+            if !stopped_short {
+                self.next_token();
+            }
         }
 
-        block
+        self.block_depth -= 1;
+        self.nesting_depth = nesting_depth;
+
+        if self.current_token_type_is(TokenType::EOF) {
+            return Err(ParseError::Unterminated {
+                expected: TokenType::RIGHT_BRACE,
+                span: self.current_token.span,
+                opened: Some(block.token.span),
+            });
+        }
+
+        block.span = self.span_from(block.span);
+
+        Ok(block)
     }
 
-    fn parse_stmt(&mut self) -> Option<Box<dyn ast::Statement>> {
-        let ret = match self.current_token.token_type {
+    /// parse_stmt parses a single statement. If it fails, the error is recorded, the parser
+    /// skips ahead to the next point it can resume from, and an ErrorStatement covering the
+    /// skipped tokens takes the statement's place.
+    fn parse_stmt(&mut self) -> ast::Statement {
+        let token = self.current_token.clone();
+
+        let result = match token.token_type {
             TokenType::LET => parse_let_stmt(self),
             TokenType::CONST => parse_const_stmt(self),
             TokenType::RETURN => parse_return_stmt(self),
             _ => parse_expr_stmt(self),
         };
 
-        Some(ret)
+        match result {
+            Ok(stmt) => stmt,
+            Err(error) => {
+                // A `}` can't start an expression, but it may well close the enclosing block.
+                // Leave it for parse_block_stmt instead of skipping over it
+                let at_closing_brace = matches!(
+                    error,
+                    ParseError::MissingPrefixFunction {
+                        found: TokenType::RIGHT_BRACE,
+                        ..
+                    }
+                );
+                // Likewise a statement keyword the error stopped on (e.g. the second `let` in
+                // `let x = let y = 1;`) starts the next statement, so it is left for the enclosing
+                // loop too. The keyword that began this statement doesn't count
+                let at_next_statement = self.current_token.span != token.span
+                    && matches!(
+                        self.current_token.token_type,
+                        TokenType::LET | TokenType::CONST | TokenType::RETURN
+                    );
+
+                if !self.reported_by_lexer(&error) {
+                    self.errors.push(error);
+                }
+
+                if !at_closing_brace && !at_next_statement {
+                    self.synchronize();
+                }
+
+                let span = if at_next_statement {
+                    token.span.to(self.prev_token.span)
+                } else {
+                    self.span_from(token.span)
+                };
+
+                ast::Statement::Error(ast::ErrorStatement { span, token })
+            }
+        }
+    }
+
+    /// stopped_short reports whether `stmt` is an ErrorStatement that ended before the current
+    /// token, which then begins the next statement and must not be skipped
+    fn stopped_short(&self, stmt: &ast::Statement) -> bool {
+        match stmt {
+            ast::Statement::Error(stmt) => stmt.span.end <= self.current_token.span.start,
+            _ => false,
+        }
+    }
+
+    /// synchronize skips tokens after a parse error until parsing can safely resume: the end of
+    /// the statement (`;`), just before the end of the enclosing block (`}`), or just before the
+    /// keyword starting the next statement. Braces opened within the skipped tokens, or by the `{`
+    /// the error stopped on, are skipped along with their matching `}`. This keeps one mistake from
+    /// cascading into many errors.
+    fn synchronize(&mut self) {
+        let mut nesting = if self.current_token_type_is(TokenType::LEFT_BRACE) {
+            1
+        } else {
+            0
+        };
+
+        loop {
+            if self.current_token_type_is(TokenType::EOF)
+                || (nesting == 0 && self.current_token_type_is(TokenType::SEMICOLON))
+            {
+                return;
+            }
+
+            match self.peek_token.token_type {
+                TokenType::EOF => return,
+                TokenType::RIGHT_BRACE if nesting == 0 && self.block_depth > 0 => return,
+                TokenType::LET | TokenType::CONST | TokenType::RETURN if nesting == 0 => return,
+                _ => self.next_token(),
+            }
+
+            match self.current_token.token_type {
+                TokenType::LEFT_BRACE => nesting += 1,
+                TokenType::RIGHT_BRACE if nesting > 0 => nesting -= 1,
+                _ => {}
+            }
+        }
     }
 
-    fn parse_function_params(&mut self) -> Vec<ast::Identifier> {
+    fn parse_function_params(&mut self) -> ParseResult<Vec<ast::Identifier>> {
+        let opened = self.current_token.span;
         let mut identifiers: Vec<ast::Identifier> = vec![];
 
         if self.peek_token_type_is(TokenType::RIGHT_PAREN) {
             self.next_token();
-            return identifiers;
+            return Ok(identifiers);
         }
 
-        self.next_token();
+        self.expect_peek_type(TokenType::IDENTIFIER)?;
 
-        identifiers.push(ast::Identifier{
+        identifiers.push(ast::Identifier {
             token: self.current_token.clone(),
+            span: self.current_token.span,
             value: self.current_token.literal.clone(),
         });
 
         while self.peek_token_type_is(TokenType::COMMA) {
             self.next_token();
-            self.next_token();
+            self.expect_peek_type(TokenType::IDENTIFIER)?;
 
-            identifiers.push(ast::Identifier{
+            identifiers.push(ast::Identifier {
                 token: self.current_token.clone(),
+                span: self.current_token.span,
                 value: self.current_token.literal.clone(),
             })
         }
 
-        if !self.expect_peek_type(TokenType::RIGHT_PAREN) {
-            return vec![];
-        }
+        self.expect_closing(TokenType::RIGHT_PAREN, opened)?;
 
-        identifiers
+        Ok(identifiers)
     }
 
-    fn parse_expr_list(&mut self, end: TokenType) -> Vec<Box<dyn ast::Expression>> {
-        let mut list: Vec<Box<dyn ast::Expression>> = vec![Box::new(ast::ZeroValueExpression{})];
+    fn parse_expr_list(&mut self, end: TokenType) -> ParseResult<Vec<ast::Expression>> {
+        let opened = self.current_token.span;
+        let mut list: Vec<ast::Expression> = vec![];
 
         if self.peek_token_type_is(end) {
             self.next_token();
-            return list;
+            return Ok(list);
         }
 
         self.next_token();
-
-        let expr = match self.parse_expr(LOWEST) {
-            Some(expr) => expr,
-            _ => {
-                let msg = format!(
-                    "Line {}: Failed to parse expression {}.",
-                    self.current_token.line, self.current_token.literal
-                );
-                self.errors.push(msg);
-                Box::new(ast::ZeroValueExpression {})
-            }
-        };
-
-        list.push(expr);
+        list.push(self.parse_expr(LOWEST)?);
 
         while self.peek_token_type_is(TokenType::COMMA) {
             self.next_token();
-            self.next_token();
 
-            let expr = match self.parse_expr(LOWEST) {
-                Some(expr) => expr,
-                _ => {
-                    let msg = format!(
-                        "Line {}: Failed to parse expression {}.",
-                        self.current_token.line, self.current_token.literal
-                    );
-                    self.errors.push(msg);
-                    Box::new(ast::ZeroValueExpression {})
-                }
-            };
+            // Allow a trailing comma before the closing delimiter
+            if self.peek_token_type_is(end) {
+                break;
+            }
 
-            list.push(expr);
+            self.next_token();
+            list.push(self.parse_expr(LOWEST)?);
         }
 
-        if self.expect_peek_type(end) {
-            return vec![Box::new(ast::ZeroValueExpression {})];
-        }
+        self.expect_closing(end, opened)?;
 
-        list
+        Ok(list)
     }
 
     fn peek_token_type_is(&self, token_type: TokenType) -> bool {
@@ -304,37 +538,61 @@ impl Parser {
     }
 
     fn peek_token_precedence(&self) -> usize {
-        match Precedences::all().get(&self.peek_token.token_type) {
-            Some(precedence) => return *precedence,
-            _ => return LOWEST,
-        };
+        precedence(self.peek_token.token_type)
     }
 
-    fn expect_peek_type(&mut self, token_type: TokenType) -> bool {
+    fn expect_peek_type(&mut self, token_type: TokenType) -> ParseResult<()> {
         if self.peek_token_type_is(token_type) {
             self.next_token();
-            return true;
+            return Ok(());
         }
 
-        self.peek_error(token_type);
+        Err(self.peek_error(token_type))
+    }
 
-        return false;
+    /// expect_closing is expect_peek_type for a closing delimiter. If the input ends first, the
+    /// error also points back at the token that opened the construct
+    fn expect_closing(&mut self, token_type: TokenType, opened: Span) -> ParseResult<()> {
+        match self.expect_peek_type(token_type) {
+            Err(ParseError::Unterminated { expected, span, .. }) => Err(ParseError::Unterminated {
+                expected,
+                span,
+                opened: Some(opened),
+            }),
+            result => result,
+        }
     }
 
-    fn peek_error(&mut self, token_type: TokenType) {
-        let msg = format!(
-            "Line {}: Expected token to be {}, but found, {}",
-            self.current_token.line, token_type, self.peek_token.literal,
-        );
-        self.errors.push(msg);
+    fn peek_error(&self, token_type: TokenType) -> ParseError {
+        if self.peek_token_type_is(TokenType::EOF) {
+            return ParseError::Unterminated {
+                expected: token_type,
+                span: self.peek_token.span,
+                opened: None,
+            };
+        }
+
+        ParseError::UnexpectedToken {
+            expected: token_type,
+            found: self.peek_token.token_type,
+            literal: self.peek_token.literal.clone(),
+            span: self.peek_token.span,
+        }
     }
 
-    fn no_prefix_parse_func_error(&mut self, token: Token) {
-        let msg = format!(
-            "Line {}: No prefix parse function for {} found",
-            self.current_token.line, token.literal
-        );
-        self.errors.push(msg);
+    fn no_prefix_parse_func_error(&self) -> ParseError {
+        ParseError::MissingPrefixFunction {
+            found: self.current_token.token_type,
+            literal: self.current_token.literal.clone(),
+            span: self.current_token.span,
+        }
+    }
+
+    /// span_from returns a Span from `start` to the end of the current token. Parse functions
+    /// leave the current token on the last token of the construct they parsed, so this is the
+    /// span of the whole construct
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.current_token.span)
     }
 
     fn current_token_type_is(&self, token_type: TokenType) -> bool {
@@ -342,14 +600,11 @@ impl Parser {
     }
 
     fn current_token_precedence(&self) -> usize {
-        match Precedences::all().get(&self.current_token.token_type) {
-            Some(precedence) => *precedence,
-            None => LOWEST,
-        }
+        precedence(self.current_token.token_type)
     }
 }
 
-fn parse_identifier(parser: &mut Parser) -> Box<dyn ast::Expression> {
+fn parse_identifier(parser: &mut Parser) -> ParseResult<ast::Expression> {
     let contains_key = parser
         .postfix_parse_funcs
         .contains_key(&parser.peek_token.token_type);
@@ -360,410 +615,785 @@ fn parse_identifier(parser: &mut Parser) -> Box<dyn ast::Expression> {
         return postfix(parser);
     }
 
-    Box::new(ast::Identifier {
+    Ok(ast::Expression::Identifier(ast::Identifier {
         token: parser.current_token.clone(),
+        span: parser.current_token.span,
         value: parser.current_token.literal.clone(),
-    })
+    }))
 }
 
-fn parse_integer_literal(parser: &mut Parser) -> Box<dyn ast::Expression> {
-    Box::new(ast::IntegerLiteral {
-        token: parser.current_token.clone(),
-        value: parser.current_token.literal.parse::<usize>().unwrap(),
-    })
-}
+/// parse_integer_literal works out the value of an integer written in any radix the lexer
+/// supports, and rejects literals that don't fit in an i64. A literal is never negative, `-5` is a
+/// PrefixExpression, so the largest allowed is i64::MAX
+fn parse_integer_literal(parser: &mut Parser) -> ParseResult<ast::Expression> {
+    let literal = &parser.current_token.literal;
+    let (digits, radix) = match literal.get(..2) {
+        Some("0x") => (&literal[2..], 16),
+        Some("0b") => (&literal[2..], 2),
+        Some("0o") => (&literal[2..], 8),
+        _ => (&literal[..], 10),
+    };
+    let digits: String = digits.chars().filter(|ch| *ch != '_').collect();
+
+    let value = match i64::from_str_radix(&digits, radix) {
+        Ok(value) => value,
+        // The lexer has already reported what is wrong with the literal, so carry on with a
+        // placeholder rather than reporting it twice
+        Err(_) if parser.lexer_reported(parser.current_token.span) => 0,
+        Err(_) => {
+            return Err(ParseError::InvalidLiteral {
+                token_type: TokenType::INTEGER,
+                literal: parser.current_token.literal.clone(),
+                span: parser.current_token.span,
+            })
+        }
+    };
 
-fn parse_prefix_expr(parser: &mut Parser) -> Box<dyn ast::Expression> {
-    let mut expr = ast::PrefixExpression {
+    Ok(ast::Expression::Integer(ast::IntegerLiteral {
         token: parser.current_token.clone(),
-        operator: parser.current_token.literal.clone(),
-        right: Box::new(ast::ZeroValueExpression {}),
-    };
+        span: parser.current_token.span,
+        value,
+    }))
+}
 
-    parser.next_token();
-    expr.right = match parser.parse_expr(PREFIX) {
-        Some(expr) => expr,
+/// parse_float_literal rejects literals too large to represent, which would otherwise parse as
+/// infinity
+fn parse_float_literal(parser: &mut Parser) -> ParseResult<ast::Expression> {
+    let literal: String = parser
+        .current_token
+        .literal
+        .chars()
+        .filter(|ch| *ch != '_')
+        .collect();
+
+    let value = match literal.parse::<f64>() {
+        Ok(value) if value.is_finite() => value,
         _ => {
-            let msg = format!(
-                "Line {}: Failed to parse expression {}.",
-                parser.current_token.line, parser.current_token.literal
-            );
-            parser.errors.push(msg);
-            Box::new(ast::ZeroValueExpression {})
+            return Err(ParseError::InvalidLiteral {
+                token_type: TokenType::FLOAT,
+                literal: parser.current_token.literal.clone(),
+                span: parser.current_token.span,
+            })
         }
     };
 
-    Box::new(expr)
+    Ok(ast::Expression::Float(ast::FloatLiteral {
+        token: parser.current_token.clone(),
+        span: parser.current_token.span,
+        value,
+    }))
 }
 
-fn parse_boolean(parser: &mut Parser) -> Box<dyn ast::Expression> {
-    Box::new(ast::Boolean {
+fn parse_prefix_expr(parser: &mut Parser) -> ParseResult<ast::Expression> {
+    let token = parser.current_token.clone();
+
+    parser.next_token();
+    let right = parser.parse_expr(PREFIX)?;
+
+    Ok(ast::Expression::Prefix(ast::PrefixExpression {
+        span: parser.span_from(token.span),
+        operator: token.literal.clone(),
+        token,
+        right: Box::new(right),
+    }))
+}
+
+fn parse_boolean(parser: &mut Parser) -> ParseResult<ast::Expression> {
+    Ok(ast::Expression::Boolean(ast::Boolean {
         token: parser.current_token.clone(),
+        span: parser.current_token.span,
         value: parser.current_token_type_is(TokenType::TRUE),
-    })
+    }))
 }
 
-fn parse_grouped_expr(parser: &mut Parser) -> Box<dyn ast::Expression> {
+fn parse_grouped_expr(parser: &mut Parser) -> ParseResult<ast::Expression> {
+    let opened = parser.current_token.span;
+
     parser.next_token();
 
-    let expr = match parser.parse_expr(LOWEST) {
-        Some(expr) => expr,
-        _ => {
-            let msg = format!(
-                "Line {}: Failed to parse expression {}.",
-                parser.current_token.line, parser.current_token.literal
-            );
-            parser.errors.push(msg);
-            Box::new(ast::ZeroValueExpression {})
-        }
-    };
+    let mut expr = parser.parse_expr(LOWEST)?;
 
-    if !parser.expect_peek_type(TokenType::RIGHT_PAREN) {
-        return Box::new(ast::ZeroValueExpression {});
-    }
+    parser.expect_closing(TokenType::RIGHT_PAREN, opened)?;
+
+    // The parentheses are part of the expression as written, so an operator applied to it spans
+    // them too, e.g. `(a + b) * c` covers the opening parenthesis
+    expr.set_span(parser.span_from(opened));
 
-    expr
+    Ok(expr)
 }
 
-fn parse_if_expr(parser: &mut Parser) -> Box<dyn ast::Expression> {
-    let mut expr = ast::IfExpression {
-        token: parser.current_token.clone(),
-        condition: Box::new(ast::ZeroValueExpression {}),
-        consequence: ast::BlockStatement {
-            token: parser.current_token.clone(),
-            statements: vec![],
-        },
-        alternative: ast::BlockStatement {
-            token: parser.current_token.clone(),
-            statements: vec![],
-        },
-    };
+fn parse_if_expr(parser: &mut Parser) -> ParseResult<ast::Expression> {
+    let token = parser.current_token.clone();
 
-    if !parser.expect_peek_type(TokenType::LEFT_PAREN) {
-        return Box::new(ast::ZeroValueExpression {});
-    }
+    parser.expect_peek_type(TokenType::LEFT_PAREN)?;
+    let opened = parser.current_token.span;
 
     parser.next_token();
-    expr.condition = match parser.parse_expr(LOWEST) {
-        Some(cond) => cond,
-        _ => {
-            let msg = format!(
-                "Line {}: Failed to parse expression {}.",
-                parser.current_token.line, parser.current_token.literal
-            );
-            parser.errors.push(msg);
-            Box::new(ast::ZeroValueExpression {})
-        }
-    };
+    let condition = parser.parse_expr(LOWEST)?;
 
-    if !parser.expect_peek_type(TokenType::RIGHT_PAREN) {
-        return Box::new(ast::ZeroValueExpression {});
-    }
+    parser.expect_closing(TokenType::RIGHT_PAREN, opened)?;
+    parser.expect_peek_type(TokenType::LEFT_BRACE)?;
 
-    if !parser.expect_peek_type(TokenType::LEFT_BRACE) {
-        return Box::new(ast::ZeroValueExpression {});
-    }
-
-    expr.consequence = parser.parse_block_stmt();
+    let consequence = parser.parse_block_stmt()?;
+    let mut alternative = None;
 
     if parser.peek_token_type_is(TokenType::ELSE) {
         parser.next_token();
-
-        if !parser.expect_peek_type(TokenType::LEFT_BRACE) {
-            return Box::new(ast::ZeroValueExpression {});
-        }
-
-        expr.alternative = parser.parse_block_stmt();
+        parser.expect_peek_type(TokenType::LEFT_BRACE)?;
+        alternative = Some(parser.parse_block_stmt()?);
     }
 
-    Box::new(expr)
+    Ok(ast::Expression::If(ast::IfExpression {
+        span: parser.span_from(token.span),
+        token,
+        condition: Box::new(condition),
+        consequence,
+        alternative,
+    }))
 }
 
-fn parse_let_stmt(parser: &mut Parser) -> Box<dyn ast::Statement> {
-    let zero_value_token: Token = Token {
-        token_type: TokenType::NONE,
-        literal: "".to_owned(),
-        line: 0,
-    };
-    let zero_value_identifier: ast::Identifier = ast::Identifier {
-        token: zero_value_token,
-        value: "".to_owned(),
-    };
-    let mut stmt = ast::LetStatement {
-        token: parser.current_token.clone(),
-        name: zero_value_identifier,
-        value: Box::new(ast::ZeroValueExpression {}),
-    };
-
-    if !parser.expect_peek_type(TokenType::IDENTIFIER) {
-        return Box::new(ast::ZeroValueStatement {});
-    }
+/// parse_binding parses the `<identifier> = <expression>;` shared by let and const statements
+fn parse_binding(parser: &mut Parser) -> ParseResult<(ast::Identifier, ast::Expression)> {
+    parser.expect_peek_type(TokenType::IDENTIFIER)?;
 
-    stmt.name = ast::Identifier {
+    let name = ast::Identifier {
         token: parser.current_token.clone(),
+        span: parser.current_token.span,
         value: parser.current_token.literal.clone(),
     };
 
-    if !parser.expect_peek_type(TokenType::EQUAL) {
-        return Box::new(ast::ZeroValueStatement {});
-    }
-
+    parser.expect_peek_type(TokenType::EQUAL)?;
     parser.next_token();
 
-    stmt.value = match parser.parse_expr(LOWEST) {
-        Some(expr) => expr,
-        _ => {
-            let msg = format!(
-                "Line {}: Failed to parse expression {}.",
-                parser.current_token.line, parser.current_token.literal
-            );
-            parser.errors.push(msg);
-            Box::new(ast::ZeroValueExpression {})
-        }
-    };
+    let mut value = parser.parse_expr(LOWEST)?;
 
-    // TODO: Handle function literal piece here
-    // if fl, ok := stmt.Value.(*ast.FunctionLiteral); ok {
-    // 	fl.Name = stmt.Name.Value
-    // }
+    if let ast::Expression::Function(lit) = &mut value {
+        lit.name = name.value.clone();
+    }
 
     if parser.peek_token_type_is(TokenType::SEMICOLON) {
         parser.next_token();
     }
 
-    Box::new(stmt)
+    Ok((name, value))
 }
 
-fn parse_const_stmt(parser: &mut Parser) -> Box<dyn ast::Statement> {
-    let zero_value_token: Token = Token {
-        token_type: TokenType::NONE,
-        literal: "".to_owned(),
-        line: 0,
-    };
-    let zero_value_identifier: ast::Identifier = ast::Identifier {
-        token: zero_value_token,
-        value: "".to_owned(),
-    };
-    let mut stmt = ast::ConstStatement {
-        token: parser.current_token.clone(),
-        name: zero_value_identifier,
-        value: Box::new(ast::ZeroValueExpression {}),
-    };
-
-    if !parser.expect_peek_type(TokenType::IDENTIFIER) {
-        return Box::new(ast::ZeroValueStatement {});
-    }
+fn parse_let_stmt(parser: &mut Parser) -> ParseResult<ast::Statement> {
+    let token = parser.current_token.clone();
+    let doc = parser.doc_comment();
+    let (name, value) = parse_binding(parser)?;
+
+    Ok(ast::Statement::Let(ast::LetStatement {
+        span: parser.span_from(token.span),
+        token,
+        name,
+        value,
+        doc,
+    }))
+}
 
-    stmt.name = ast::Identifier {
-        token: parser.current_token.clone(),
-        value: parser.current_token.literal.clone(),
-    };
+fn parse_const_stmt(parser: &mut Parser) -> ParseResult<ast::Statement> {
+    let token = parser.current_token.clone();
+    let doc = parser.doc_comment();
+    let (name, value) = parse_binding(parser)?;
+
+    Ok(ast::Statement::Const(ast::ConstStatement {
+        span: parser.span_from(token.span),
+        token,
+        name,
+        value,
+        doc,
+    }))
+}
 
-    if !parser.expect_peek_type(TokenType::EQUAL) {
-        return Box::new(ast::ZeroValueStatement {});
-    }
+fn parse_return_stmt(parser: &mut Parser) -> ParseResult<ast::Statement> {
+    let token = parser.current_token.clone();
 
     parser.next_token();
 
-    stmt.value = match parser.parse_expr(LOWEST) {
-        Some(expr) => expr,
-        _ => {
-            let msg = format!(
-                "Line {}: Failed to parse expression {}.",
-                parser.current_token.line, parser.current_token.literal
-            );
-            parser.errors.push(msg);
-            Box::new(ast::ZeroValueExpression {})
-        }
-    };
-
-    // TODO: Handle function literal piece here
-    // if fl, ok := stmt.Value.(*ast.FunctionLiteral); ok {
-    // 	fl.Name = stmt.Name.Value
-    // }
+    let return_value = parser.parse_expr(LOWEST)?;
 
     if parser.peek_token_type_is(TokenType::SEMICOLON) {
         parser.next_token();
     }
 
-    Box::new(stmt)
+    Ok(ast::Statement::Return(ast::ReturnStatement {
+        span: parser.span_from(token.span),
+        token,
+        return_value,
+    }))
 }
 
-fn parse_return_stmt(parser: &mut Parser) -> Box<dyn ast::Statement> {
-    let mut stmt = ast::ReturnStatement {
-        token: parser.current_token.clone(),
-        return_value: Box::new(ast::ZeroValueExpression {}),
-    };
-
-    parser.next_token();
+fn parse_expr_stmt(parser: &mut Parser) -> ParseResult<ast::Statement> {
+    let token = parser.current_token.clone();
 
-    stmt.return_value = match parser.parse_expr(LOWEST) {
-        Some(expr) => expr,
-        _ => {
-            let msg = format!(
-                "Line {}: Failed to parse expression {}.",
-                parser.current_token.line, parser.current_token.literal,
-            );
-            parser.errors.push(msg);
-            Box::new(ast::ZeroValueExpression {})
-        }
-    };
+    let expression = parser.parse_expr(LOWEST)?;
 
     if parser.peek_token_type_is(TokenType::SEMICOLON) {
         parser.next_token();
     }
 
-    Box::new(stmt)
+    Ok(ast::Statement::Expression(ast::ExpressionStatement {
+        span: parser.span_from(token.span),
+        token,
+        expression,
+    }))
+}
+
+fn parse_function_literal(parser: &mut Parser) -> ParseResult<ast::Expression> {
+    let token = parser.current_token.clone();
+
+    parser.expect_peek_type(TokenType::LEFT_PAREN)?;
+
+    let parameters = parser.parse_function_params()?;
+
+    parser.expect_peek_type(TokenType::LEFT_BRACE)?;
+
+    let body = Rc::new(parser.parse_block_stmt()?);
+
+    Ok(ast::Expression::Function(ast::FunctionLiteral {
+        span: parser.span_from(token.span),
+        token,
+        parameters,
+        body,
+        name: "".to_owned(),
+    }))
 }
 
-fn parse_expr_stmt(parser: &mut Parser) -> Box<dyn ast::Statement> {
-    let mut stmt = ast::ExpressionStatement {
+fn parse_string_literal(parser: &mut Parser) -> ParseResult<ast::Expression> {
+    Ok(ast::Expression::String(ast::StringLiteral {
         token: parser.current_token.clone(),
-        expression: Box::new(ast::ZeroValueExpression {}),
-    };
+        span: parser.current_token.span,
+        value: parser.current_token.literal.clone(),
+    }))
+}
 
-    stmt.expression = match parser.parse_expr(LOWEST) {
-        Some(expr) => expr,
-        _ => {
-            let msg = format!(
-                "Line {}: Failed to parse expression {}.",
-                parser.current_token.line, parser.current_token.literal,
-            );
-            parser.errors.push(msg);
-            Box::new(ast::ZeroValueExpression {})
-        }
-    };
+fn parse_array_literal(parser: &mut Parser) -> ParseResult<ast::Expression> {
+    let token = parser.current_token.clone();
 
-    if parser.peek_token_type_is(TokenType::SEMICOLON) {
+    let elements = parser.parse_expr_list(TokenType::RIGHT_BRACKET)?;
+
+    Ok(ast::Expression::Array(ast::ArrayLiteral {
+        span: parser.span_from(token.span),
+        token,
+        elements,
+    }))
+}
+
+fn parse_hash_literal(parser: &mut Parser) -> ParseResult<ast::Expression> {
+    let token = parser.current_token.clone();
+    let mut pairs = vec![];
+
+    while !parser.peek_token_type_is(TokenType::RIGHT_BRACE)
+        && !parser.peek_token_type_is(TokenType::EOF)
+    {
+        parser.next_token();
+        let key = parser.parse_expr(LOWEST)?;
+
+        parser.expect_peek_type(TokenType::COLON)?;
         parser.next_token();
+
+        let value = parser.parse_expr(LOWEST)?;
+
+        pairs.push((key, value));
+
+        if !parser.peek_token_type_is(TokenType::RIGHT_BRACE)
+            && !parser.peek_token_type_is(TokenType::EOF)
+        {
+            parser.expect_peek_type(TokenType::COMMA)?;
+        }
     }
 
-    Box::new(stmt)
+    parser.expect_closing(TokenType::RIGHT_BRACE, token.span)?;
+
+    Ok(ast::Expression::Hash(ast::HashLiteral {
+        span: parser.span_from(token.span),
+        token,
+        pairs,
+    }))
 }
 
-fn parse_function_literal(parser: &mut Parser) -> Box<dyn ast::Expression> {
-    let zero_value_token: Token = Token {
-        token_type: TokenType::NONE,
-        literal: "".to_owned(),
-        line: 0,
-    };
-    let mut lit = ast::FunctionLiteral{
+fn parse_infix_expr(parser: &mut Parser, left: ast::Expression) -> ParseResult<ast::Expression> {
+    let token = parser.current_token.clone();
+    let precedence = parser.current_token_precedence();
+
+    parser.next_token();
+
+    let right = parser.parse_expr(precedence)?;
+
+    Ok(ast::Expression::Infix(ast::InfixExpression {
+        span: parser.span_from(left.span()),
+        operator: token.literal.clone(),
+        token,
+        left: Box::new(left),
+        right: Box::new(right),
+    }))
+}
+
+fn parse_call_expr(parser: &mut Parser, function: ast::Expression) -> ParseResult<ast::Expression> {
+    let token = parser.current_token.clone();
+
+    let arguments = parser.parse_expr_list(TokenType::RIGHT_PAREN)?;
+
+    Ok(ast::Expression::Call(ast::CallExpression {
+        span: parser.span_from(function.span()),
+        token,
+        function: Box::new(function),
+        arguments,
+    }))
+}
+
+fn parse_index_expr(parser: &mut Parser, left: ast::Expression) -> ParseResult<ast::Expression> {
+    let token = parser.current_token.clone();
+
+    parser.next_token();
+
+    let index = parser.parse_expr(LOWEST)?;
+
+    parser.expect_closing(TokenType::RIGHT_BRACKET, token.span)?;
+
+    Ok(ast::Expression::Index(ast::IndexExpression {
+        span: parser.span_from(left.span()),
+        token,
+        left: Box::new(left),
+        index: Box::new(index),
+    }))
+}
+
+/// parse_postfix_expr is called by parse_identifier once it has advanced onto the ++ or -- token,
+/// so the identifier being incremented/decremented is the prev_token
+fn parse_postfix_expr(parser: &mut Parser) -> ParseResult<ast::Expression> {
+    Ok(ast::Expression::Postfix(ast::PostfixExpression {
         token: parser.current_token.clone(),
-        parameters: vec![],
-        body: ast::BlockStatement{
-            token: zero_value_token,
-            statements: vec![],
+        span: parser.span_from(parser.prev_token.span),
+        left: ast::Identifier {
+            token: parser.prev_token.clone(),
+            span: parser.prev_token.span,
+            value: parser.prev_token.literal.clone(),
         },
-        name: "".to_owned(),
-    };
+        operator: parser.current_token.literal.clone(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_tools::error::LexError;
+
+    fn parse(source: &str) -> (ast::RootNode, Vec<ParseError>) {
+        let mut parser = Parser::new(Lexer::new(source));
+        let program = parser.parse_program();
+
+        (program, parser.errors().to_vec())
+    }
 
-    if !parser.expect_peek_type(TokenType::LEFT_PAREN) {
-        return Box::new(ast::ZeroValueExpression {});
+    #[test]
+    fn operator_precedence() {
+        let tests = [
+            ("a || b && c", "(a || (b && c));"),
+            ("a && b || c", "((a && b) || c);"),
+            ("a || b || c", "((a || b) || c);"),
+            ("a == b && c != d", "((a == b) && (c != d));"),
+            ("a < b == c > d", "((a < b) == (c > d));"),
+            ("a + b * c % d", "(a + (b * (c % d)));"),
+            ("-a * b", "((-a) * b);"),
+            ("!f(a)[0]", "(!(f(a)[0]));"),
+        ];
+
+        for (source, expected) in tests.iter() {
+            let (program, errors) = parse(source);
+
+            assert!(errors.is_empty(), "{}: {:?}", source, errors);
+            assert_eq!(program.string(), *expected, "{}", source);
+        }
     }
 
-    lit.parameters = parser.parse_function_params();
+    #[test]
+    fn errors_are_classified() {
+        let (_, errors) = parse("let = 5;");
+        assert_eq!(
+            errors,
+            [ParseError::UnexpectedToken {
+                expected: TokenType::IDENTIFIER,
+                found: TokenType::EQUAL,
+                literal: "=".to_owned(),
+                span: Span::new(4, 5, 1, 5),
+            }]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "expected IDENTIFIER, found EQUAL `=`"
+        );
+
+        let (_, errors) = parse("let x = ;");
+        assert_eq!(
+            errors,
+            [ParseError::MissingPrefixFunction {
+                found: TokenType::SEMICOLON,
+                literal: ";".to_owned(),
+                span: Span::new(8, 9, 1, 9),
+            }]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "expected an expression, found SEMICOLON `;`"
+        );
+
+        let (_, errors) = parse("if (x) {\n  1");
+        assert_eq!(
+            errors,
+            [ParseError::Unterminated {
+                expected: TokenType::RIGHT_BRACE,
+                span: Span::new(12, 12, 2, 4),
+                opened: Some(Span::new(7, 8, 1, 8)),
+            }]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "unexpected end of input, expected RIGHT_BRACE"
+        );
 
-    if !parser.expect_peek_type(TokenType::LEFT_BRACE) {
-        return Box::new(ast::ZeroValueExpression {});
+        let (_, errors) = parse("(1 + 2");
+        assert_eq!(
+            errors,
+            [ParseError::Unterminated {
+                expected: TokenType::RIGHT_PAREN,
+                span: Span::new(6, 6, 1, 7),
+                opened: Some(Span::new(0, 1, 1, 1)),
+            }]
+        );
+
+        let (_, errors) = parse("9223372036854775808");
+        assert_eq!(
+            errors,
+            [ParseError::InvalidLiteral {
+                token_type: TokenType::INTEGER,
+                literal: "9223372036854775808".to_owned(),
+                span: Span::new(0, 19, 1, 1),
+            }]
+        );
     }
 
-    lit.body = parser.parse_block_stmt();
+    #[test]
+    fn lexer_errors_are_not_reported_twice() {
+        let (_, errors) = parse("let x = @;");
+        let lex_error = LexError::UnexpectedCharacter {
+            character: '@',
+            span: Span::new(8, 9, 1, 9),
+        };
 
-    Box::new(lit)
-}
+        // The ILLEGAL token can't begin an expression either, but that adds nothing
+        assert_eq!(errors, [ParseError::Lex(lex_error.clone())]);
+        assert_eq!(errors[0].span(), lex_error.span());
+        assert_eq!(errors[0].to_string(), "unexpected character `@`");
+        assert!(std::error::Error::source(&errors[0]).is_some());
+
+        let (_, errors) = parse("a & b;");
+        assert_eq!(
+            errors,
+            [ParseError::Lex(LexError::LoneOperator {
+                operator: '&',
+                span: Span::new(2, 3, 1, 3),
+            })]
+        );
+    }
 
-fn parse_string_literal(parser: &mut Parser) -> Box<dyn ast::Expression> {
-    Box::new(ast::StringLiteral{
-        token: parser.current_token.clone(),
-        value: parser.current_token.literal.clone(),
-    })
-}
+    #[test]
+    fn integer_literal_values() {
+        let tests = [
+            ("0xff", 255),
+            ("0b1010", 10),
+            ("0o17", 15),
+            ("1_000_000", 1_000_000),
+            ("0x7fff_ffff_ffff_ffff", i64::MAX),
+        ];
+
+        for (source, value) in tests.iter() {
+            let (program, errors) = parse(source);
+
+            assert!(errors.is_empty(), "{}: {:?}", source, errors);
+            match expression(&program) {
+                ast::Expression::Integer(literal) => assert_eq!(literal.value, *value),
+                other => panic!("expected an integer literal, got {}", other),
+            }
+        }
+    }
 
-fn parse_array_literal(parser: &mut Parser) -> Box<dyn ast::Expression> {
-    let mut array = ast::ArrayLiteral{
-        token: parser.current_token.clone(),
-        elements: vec![],
-    };
+    #[test]
+    fn malformed_integer_literals_are_reported_once() {
+        for source in ["0x", "0b102", "1__0"].iter() {
+            let (_, errors) = parse(source);
 
-    array.elements = parser.parse_expr_list(TokenType::RIGHT_BRACKET);
+            assert_eq!(errors.len(), 1, "{}: {:?}", source, errors);
+            assert!(matches!(errors[0], ParseError::Lex(_)), "{}", source);
+        }
+    }
 
-    Box::new(array)
-}
+    /// docs returns the doc comment of each let and const statement in `statements`
+    fn docs(statements: &[ast::Statement]) -> Vec<Option<&str>> {
+        statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                ast::Statement::Let(stmt) => Some(stmt.doc.as_deref()),
+                ast::Statement::Const(stmt) => Some(stmt.doc.as_deref()),
+                _ => None,
+            })
+            .collect()
+    }
 
-fn parse_hash_literal(parser: &mut Parser) -> Box<dyn ast::Expression> {
-    let mut hash = ast::HashLiteral{
-        token: parser.current_token.clone(),
-        pairs: HashMap::new(),
-    };
+    #[test]
+    fn doc_comments_attach_to_the_binding_after_them() {
+        let (program, errors) = parse(
+            "/// Adds
+///   two numbers
+let add = 1;
+///no space
+const c = 2;
+let undocumented = 3;
+///
+let empty = 4;",
+        );
 
-    while !parser.peek_token_type_is(TokenType::RIGHT_BRACE) {
-        parser.next_token();
-        let key = match parser.parse_expr(LOWEST) {
-            Some(expr) => expr,
-            _ => {
-                let msg = format!(
-                    "Line {}: Failed to parse expression {}.",
-                    parser.current_token.line, parser.current_token.literal,
-                );
-                parser.errors.push(msg);
-                Box::new(ast::ZeroValueExpression {})
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(
+            docs(&program.statements),
+            [
+                Some("Adds\n  two numbers"),
+                Some("no space"),
+                None,
+                Some(""),
+            ]
+        );
+    }
+
+    #[test]
+    fn doc_comments_inside_blocks() {
+        let (program, _) = parse("if (x) {\n    /// inner\n    let a = 1;\n    a\n}");
+
+        match expression(&program) {
+            ast::Expression::If(if_expr) => {
+                assert_eq!(docs(&if_expr.consequence.statements), [Some("inner")]);
             }
-        };
-        
-        if !parser.expect_peek_type(TokenType::COLON) {
-            return Box::new(ast::ZeroValueExpression {});
+            other => panic!("expected an if expression, got {}", other),
+        }
+    }
+
+    #[test]
+    fn comments_that_are_not_doc_comments() {
+        let tests = [
+            "// plain\nlet a = 1;",
+            "//// four slashes\nlet a = 1;",
+            "/** block */\nlet a = 1;",
+            // A plain comment ends the run of doc comment lines
+            "/// doc\n// plain\nlet a = 1;",
+            "/// doc\n/* block */ let a = 1;",
+            // Only let and const take doc comments, and a doc comment before anything else
+            // doesn't carry over to the next binding
+            "/// doc\nx;\nlet a = 1;",
+            "/// doc\nreturn 1;\nlet a = 1;",
+        ];
+
+        for source in tests.iter() {
+            let (program, errors) = parse(source);
+
+            assert!(errors.is_empty(), "{}: {:?}", source, errors);
+            assert_eq!(docs(&program.statements), [None], "{}", source);
         }
+    }
 
-        parser.next_token();
+    /// expression returns the expression of the first statement, which must be an expression
+    /// statement
+    fn expression(program: &ast::RootNode) -> &ast::Expression {
+        match &program.statements[0] {
+            ast::Statement::Expression(stmt) => &stmt.expression,
+            other => panic!("expected an expression statement, got {}", other),
+        }
+    }
 
-        let value = match parser.parse_expr(LOWEST) {
-            Some(expr) => expr,
-            _ => {
-                let msg = format!(
-                    "Line {}: Failed to parse expression {}.",
-                    parser.current_token.line, parser.current_token.literal,
-                );
-                parser.errors.push(msg);
-                Box::new(ast::ZeroValueExpression {})
+    #[test]
+    fn grouped_expressions_span_their_parentheses() {
+        let source_of = |source: &'static str, span: Span| &source[span.start..span.end];
+
+        let source = "(a + b) * c";
+        let (program, errors) = parse(source);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let product = expression(&program);
+        assert_eq!(source_of(source, product.span()), "(a + b) * c");
+        match product {
+            ast::Expression::Infix(infix) => {
+                assert_eq!(source_of(source, infix.left.span()), "(a + b)");
+                assert_eq!(infix.left.span(), Span::new(0, 7, 1, 1));
+                assert_eq!(source_of(source, infix.right.span()), "c");
+            }
+            other => panic!("expected an infix expression, got {}", other),
+        }
+
+        let tests = [
+            ("a * (b + c)", "a * (b + c)"),
+            ("((a))", "((a))"),
+            ("-(a)", "-(a)"),
+            ("f((a), b)", "f((a), b)"),
+            ("(f)(x)", "(f)(x)"),
+        ];
+        for (source, expected) in tests.iter() {
+            let (program, errors) = parse(source);
+
+            assert!(errors.is_empty(), "{}: {:?}", source, errors);
+            assert_eq!(source_of(source, expression(&program).span()), *expected);
+        }
+    }
+
+    /// kinds names each statement's kind, so tests can check what survived recovery
+    fn kinds(statements: &[ast::Statement]) -> Vec<&'static str> {
+        statements
+            .iter()
+            .map(|stmt| match stmt {
+                ast::Statement::Let(_) => "let",
+                ast::Statement::Const(_) => "const",
+                ast::Statement::Return(_) => "return",
+                ast::Statement::Expression(_) => "expression",
+                ast::Statement::Error(_) => "error",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn recovery_keeps_the_statement_after_a_missing_value() {
+        let (program, errors) = parse("let x = \nlet y = 1;\ny;");
+
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            ParseError::MissingPrefixFunction {
+                found: TokenType::LET,
+                ..
             }
+        ));
+        assert_eq!(kinds(&program.statements), ["error", "let", "expression"]);
+        assert_eq!(program.statements[1].string(), "let y = 1;");
+    }
+
+    #[test]
+    fn recovery_stops_at_each_statement_keyword() {
+        let (program, errors) = parse("let x = return 5; let y = const z = 1; z");
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            kinds(&program.statements),
+            ["error", "return", "error", "const", "expression"]
+        );
+    }
+
+    #[test]
+    fn recovery_after_an_incomplete_expression() {
+        let (program, errors) = parse("let x = 1 +; let y = 2;");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(kinds(&program.statements), ["error", "let"]);
+        assert_eq!(program.statements[1].string(), "let y = 2;");
+    }
+
+    #[test]
+    fn recovery_reports_one_error_per_typo() {
+        let (program, errors) = parse("let a = ; let b = 2; let = 3; let c 4; b + c");
+
+        assert_eq!(errors.len(), 3);
+        assert_eq!(
+            kinds(&program.statements),
+            ["error", "let", "error", "error", "expression"]
+        );
+    }
+
+    #[test]
+    fn recovery_inside_a_block() {
+        let (program, errors) = parse("if (x) { let a = let b = 2; b } let c = 3;");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(kinds(&program.statements), ["expression", "let"]);
+
+        let block = match &program.statements[0] {
+            ast::Statement::Expression(stmt) => match &stmt.expression {
+                ast::Expression::If(if_expr) => &if_expr.consequence,
+                other => panic!("expected an if expression, got {}", other),
+            },
+            other => panic!("expected an expression statement, got {}", other),
         };
+        assert_eq!(kinds(&block.statements), ["error", "let", "expression"]);
+    }
 
-        hash.pairs.insert(key.string(), value);
+    #[test]
+    fn error_statement_stops_before_the_next_keyword() {
+        let source = "let x = let y = 1;";
+        let (program, _) = parse(source);
 
-        if !parser.peek_token_type_is(TokenType::RIGHT_BRACE) && !parser.expect_peek_type(TokenType::COMMA) {
-            return Box::new(ast::ZeroValueExpression {});
-        }
+        let span = program.statements[0].span();
+        assert_eq!(&source[span.start..span.end], "let x =");
     }
 
-    if !parser.peek_token_type_is(TokenType::RIGHT_BRACE) && !parser.expect_peek_type(TokenType::COMMA) {
-        return Box::new(ast::ZeroValueExpression {});
+    #[test]
+    fn recovery_skips_a_nested_block() {
+        let (program, errors) = parse("if (a) { 1 + + func() { 2 } } let b = 3;");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(kinds(&program.statements), ["expression", "let"]);
     }
 
-    Box::new(hash)
-}
+    #[test]
+    fn nesting_within_the_limit() {
+        let source = format!("{}1{}", "(".repeat(100), ")".repeat(100));
+        let (program, errors) = parse(&source);
 
-fn parse_infix_expr(parser: &mut Parser, left: Box<dyn ast::Expression>) -> Box<dyn ast::Expression> {
-    let mut expr = ast::InfixExpression{
-        token: parser.current_token.clone(),
-        operator: parser.current_token.literal.clone(),
-        left,
-        right: Box::new(ast::ZeroValueExpression {}),
-    };
-    let precedence = parser.current_token_precedence();
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(program.string(), "1;");
+    }
 
-    parser.next_token();
+    #[test]
+    fn nesting_too_deep_is_one_error() {
+        let source = format!("{}1{}; let y = 2;", "(".repeat(5000), ")".repeat(5000));
+        let (program, errors) = parse(&source);
+
+        // The 129th parenthesis is the first one too deep
+        assert_eq!(
+            errors,
+            [ParseError::NestingTooDeep {
+                limit: MAX_NESTING_DEPTH,
+                span: Span::new(128, 129, 1, 129),
+            }]
+        );
+        assert_eq!(kinds(&program.statements), ["error", "let"]);
+    }
 
-    expr.right = match parser.parse_expr(precedence) {
-        Some(expr) => expr,
-        _ => {
-            let msg = format!(
-                "Line {}: Failed to parse expression {}.",
-                parser.current_token.line, parser.current_token.literal,
-            );
-            parser.errors.push(msg);
-            Box::new(ast::ZeroValueExpression {})
+    #[test]
+    fn nesting_too_deep_in_blocks_is_one_error() {
+        let sources = [
+            format!("{}1{}", "if (true) { ".repeat(1000), " }".repeat(1000)),
+            format!(
+                "{}1{}",
+                "func() { let x = ".repeat(1000),
+                "; x }()".repeat(1000)
+            ),
+            format!("{}1{}", "[{1: ".repeat(1000), "}]".repeat(1000)),
+        ];
+
+        for source in sources.iter() {
+            let (program, errors) = parse(source);
+
+            assert_eq!(errors.len(), 1, "{:?}", errors);
+            assert!(matches!(errors[0], ParseError::NestingTooDeep { .. }));
+            assert_eq!(program.statements.len(), 1);
         }
-    };
+    }
+
+    #[test]
+    fn long_operator_chain_is_one_error() {
+        // Each operator nests the chain so far one level deeper, so an unlimited chain would build
+        // a tree too deep to walk or even drop
+        let source = format!("x{}", " + 1".repeat(200_000));
+        let (program, errors) = parse(&source);
 
-    Box::new(expr)
-}
\ No newline at end of file
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], ParseError::NestingTooDeep { .. }));
+        assert_eq!(kinds(&program.statements), ["error"]);
+    }
+}
@@ -1,7 +1,7 @@
-use std::collections::HashMap;
 use std::fmt;
 
 /// Monkey's token types
+#[allow(non_camel_case_types)]
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum TokenType {
     /// Token/character we don't know about
@@ -9,9 +9,14 @@ pub enum TokenType {
     /// End of file
     EOF,
 
+    /// Trivia, only produced by a Lexer created with Lexer::with_trivia
+    WHITESPACE,
+    COMMENT,
+
     /// Identifiers & literals
     IDENTIFIER, // add, foobar, x, y, ...
     INTEGER,
+    FLOAT, // 3.14, 1e-9, 2.5E+3
     STRING,
 
     /// Operators
@@ -60,83 +65,126 @@ pub enum TokenType {
 impl fmt::Display for TokenType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let printable = match *self {
-            ILLEGAL => "ILLEGAL",
-            EOF => "EOF",
-            IDENTIFIER => "IDENTIFIER",
-            INTEGER => "INTEGER",
-            STRING => "STRING",
-            EQUAL => "EQUAL",
-            PLUS => "PLUS",
-            PLUS_PLUS => "PLUS_PLUS",
-            MINUS => "MINUS",
-            MINUS_MINUS => "MINUS_MINUS",
-            STAR => "STAR",
-            SLASH => "SLASH",
-            MOD => "MOD",
-            BANG => "BANG",
-            EQUAL_EQUAL => "EQUAL_EQUAL",
-            LESS => "LESS",
-            LESS_EQUAL => "LESS_EQUAL",
-            GREATER => "GREATER",
-            GREATER_EQUAL => "GREATER_EQUAL",
-            BANG_EQUAL => "BANG_EQUAL",
-            AND => "AND",
-            OR => "OR",
-            COMMA => "COMMA",
-            COLON => "COLON",
-            SEMICOLON => "SEMICOLON",
-            LEFT_PAREN => "LEFT_PAREN",
-            RIGHT_PAREN => "RIGHT_PAREN",
-            LEFT_BRACE => "LEFT_BRACE",
-            RIGHT_BRACE => "RIGHT_BRACE",
-            LEFT_BRACKET => "LEFT_BRACKET",
-            RIGHT_BRACKET => "RIGHT_BRACKET",
-            FUNCTION => "FUNCTION",
-            LET => "LET",
-            CONST => "CONST",
-            TRUE => "TRUE",
-            FALSE => "FALSE",
-            IF => "IF",
-            ELSE => "ELSE",
-            RETURN => "RETURN",
-            NONE => "NONE",
+            TokenType::ILLEGAL => "ILLEGAL",
+            TokenType::EOF => "EOF",
+            TokenType::WHITESPACE => "WHITESPACE",
+            TokenType::COMMENT => "COMMENT",
+            TokenType::IDENTIFIER => "IDENTIFIER",
+            TokenType::INTEGER => "INTEGER",
+            TokenType::FLOAT => "FLOAT",
+            TokenType::STRING => "STRING",
+            TokenType::EQUAL => "EQUAL",
+            TokenType::PLUS => "PLUS",
+            TokenType::PLUS_PLUS => "PLUS_PLUS",
+            TokenType::MINUS => "MINUS",
+            TokenType::MINUS_MINUS => "MINUS_MINUS",
+            TokenType::STAR => "STAR",
+            TokenType::SLASH => "SLASH",
+            TokenType::MOD => "MOD",
+            TokenType::BANG => "BANG",
+            TokenType::EQUAL_EQUAL => "EQUAL_EQUAL",
+            TokenType::LESS => "LESS",
+            TokenType::LESS_EQUAL => "LESS_EQUAL",
+            TokenType::GREATER => "GREATER",
+            TokenType::GREATER_EQUAL => "GREATER_EQUAL",
+            TokenType::BANG_EQUAL => "BANG_EQUAL",
+            TokenType::AND => "AND",
+            TokenType::OR => "OR",
+            TokenType::COMMA => "COMMA",
+            TokenType::COLON => "COLON",
+            TokenType::SEMICOLON => "SEMICOLON",
+            TokenType::LEFT_PAREN => "LEFT_PAREN",
+            TokenType::RIGHT_PAREN => "RIGHT_PAREN",
+            TokenType::LEFT_BRACE => "LEFT_BRACE",
+            TokenType::RIGHT_BRACE => "RIGHT_BRACE",
+            TokenType::LEFT_BRACKET => "LEFT_BRACKET",
+            TokenType::RIGHT_BRACKET => "RIGHT_BRACKET",
+            TokenType::FUNCTION => "FUNCTION",
+            TokenType::LET => "LET",
+            TokenType::CONST => "CONST",
+            TokenType::TRUE => "TRUE",
+            TokenType::FALSE => "FALSE",
+            TokenType::IF => "IF",
+            TokenType::ELSE => "ELSE",
+            TokenType::RETURN => "RETURN",
+            TokenType::NONE => "NONE",
         };
         write!(f, "{}", printable)
     }
 }
 
-/// Token is a struct representing a Monkey token - holds a type and a literal
-#[derive(Clone)]
+/// Span - the region of source a token or AST node covers. start and end are byte offsets into
+/// the source (end is exclusive), line and column are 1-based and locate start.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Span {
+        Span {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    /// to returns a Span running from the start of this span to the end of `other`
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end,
+            ..self
+        }
+    }
+}
+
+/// Token is a struct representing a Monkey token - holds a type, a literal and where it was found
+#[derive(Clone, Debug, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
     pub literal: String,
-    pub line: usize,
+    pub span: Span,
 }
 
-struct Keywords;
-
-impl Keywords {
-    pub fn all() -> HashMap<String, TokenType> {
-        let mut keywords: HashMap<String, TokenType> = HashMap::new();
+/// Comment is a `//` or `/* */` comment the lexer skipped over. text includes the delimiters, so
+/// tools like the formatter can put comments back exactly as they were written
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+}
 
-        keywords.insert("func".to_owned(), TokenType::FUNCTION);
-        keywords.insert("let".to_owned(), TokenType::LET);
-        keywords.insert("const".to_owned(), TokenType::CONST);
-        keywords.insert("true".to_owned(), TokenType::TRUE);
-        keywords.insert("false".to_owned(), TokenType::FALSE);
-        keywords.insert("if".to_owned(), TokenType::IF);
-        keywords.insert("else".to_owned(), TokenType::ELSE);
-        keywords.insert("return".to_owned(), TokenType::RETURN);
+impl Comment {
+    /// doc returns the text of a `///` doc comment, without the slashes and the space after
+    /// them, or None for any other comment. `////` and longer are ordinary comments
+    pub fn doc(&self) -> Option<&str> {
+        let text = self.text.strip_prefix("///")?;
+        if text.starts_with('/') {
+            return None;
+        }
 
-        keywords
+        Some(text.strip_prefix(' ').unwrap_or(text))
     }
 }
 
-/// look_up_identifier checks our keywords map for the scanned keyword. If it finds one, then
-/// the keyword's type is returned. If not, the user defined IDENTIFIER is returned
-pub fn look_up_identifier(identifier: &String) -> TokenType {
-    if Keywords::all().contains_key(identifier) {
-        return Keywords::all()[identifier];
+/// look_up_identifier checks whether the scanned identifier is a keyword. If it is, then the
+/// keyword's type is returned. If not, the user defined IDENTIFIER is returned. This runs for
+/// every identifier lexed, so it is a match the compiler can turn into a few comparisons rather
+/// than a table built at runtime
+pub fn look_up_identifier(identifier: &str) -> TokenType {
+    match identifier {
+        "func" => TokenType::FUNCTION,
+        "let" => TokenType::LET,
+        "const" => TokenType::CONST,
+        "true" => TokenType::TRUE,
+        "false" => TokenType::FALSE,
+        "if" => TokenType::IF,
+        "else" => TokenType::ELSE,
+        "return" => TokenType::RETURN,
+        _ => TokenType::IDENTIFIER,
     }
-    TokenType::IDENTIFIER
 }
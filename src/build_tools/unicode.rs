@@ -0,0 +1,121 @@
+mod tables;
+
+/// Blocks of nonspacing combining marks, which take up no width on screen
+static COMBINING_MARKS: &[(char, char)] = &[
+    ('\u{0300}', '\u{036F}'), // Combining Diacritical Marks
+    ('\u{0483}', '\u{0487}'), // Cyrillic combining marks
+    ('\u{0591}', '\u{05BD}'), // Hebrew points and accents
+    ('\u{064B}', '\u{065F}'), // Arabic harakat
+    ('\u{0E31}', '\u{0E3A}'), // Thai vowel signs and marks
+    ('\u{0E47}', '\u{0E4E}'),
+    ('\u{1AB0}', '\u{1AFF}'), // Combining Diacritical Marks Extended
+    ('\u{1DC0}', '\u{1DFF}'), // Combining Diacritical Marks Supplement
+    ('\u{20D0}', '\u{20FF}'), // Combining Diacritical Marks for Symbols
+    ('\u{302A}', '\u{302F}'), // CJK tone marks
+    ('\u{3099}', '\u{309A}'), // Kana voicing marks
+    ('\u{FE00}', '\u{FE0F}'), // Variation Selectors
+    ('\u{FE20}', '\u{FE2F}'), // Combining Half Marks
+];
+
+/// Blocks of East Asian Wide and Fullwidth characters, which terminals draw two columns wide
+static WIDE: &[(char, char)] = &[
+    ('\u{1100}', '\u{115F}'),   // Hangul Jamo initial consonants
+    ('\u{2E80}', '\u{303E}'),   // CJK radicals, Kangxi radicals, CJK symbols and punctuation
+    ('\u{3041}', '\u{33FF}'),   // Hiragana, Katakana, Bopomofo, Hangul compatibility Jamo, ...
+    ('\u{3400}', '\u{4DBF}'),   // CJK Unified Ideographs Extension A
+    ('\u{4E00}', '\u{9FFF}'),   // CJK Unified Ideographs
+    ('\u{A000}', '\u{A4CF}'),   // Yi
+    ('\u{AC00}', '\u{D7A3}'),   // Hangul syllables
+    ('\u{F900}', '\u{FAFF}'),   // CJK Compatibility Ideographs
+    ('\u{FE30}', '\u{FE4F}'),   // CJK Compatibility Forms
+    ('\u{FF00}', '\u{FF60}'),   // Fullwidth forms
+    ('\u{FFE0}', '\u{FFE6}'),   // Fullwidth signs
+    ('\u{1F300}', '\u{1F64F}'), // Pictographs and emoticons
+    ('\u{1F900}', '\u{1F9FF}'), // Supplemental symbols and pictographs
+    ('\u{20000}', '\u{3FFFD}'), // CJK Unified Ideographs Extensions B onwards
+];
+
+/// is_xid_start reports whether `ch` has the Unicode XID_Start property, so it can begin an
+/// identifier: letters, and letter numbers such as `Ⅻ`, but no digits or combining marks
+pub fn is_xid_start(ch: char) -> bool {
+    if ch.is_ascii() {
+        return ch.is_ascii_alphabetic();
+    }
+
+    in_ranges(tables::XID_START, ch)
+}
+
+/// is_xid_continue reports whether `ch` has the Unicode XID_Continue property, so it can appear
+/// after the first character of an identifier: anything that can start one, plus digits,
+/// combining marks (e.g. the virama in `नमस्ते`) and connector punctuation such as `_`
+pub fn is_xid_continue(ch: char) -> bool {
+    if ch.is_ascii() {
+        return ch.is_ascii_alphanumeric() || ch == '_';
+    }
+
+    in_ranges(tables::XID_CONTINUE, ch)
+}
+
+/// display_width returns how many columns a terminal uses to draw `ch`: 0 for combining marks
+/// and zero width characters, 2 for wide East Asian characters and emoji, and 1 otherwise
+pub fn display_width(ch: char) -> usize {
+    if is_combining_mark(ch) || ('\u{200B}'..='\u{200F}').contains(&ch) {
+        0
+    } else if in_ranges(WIDE, ch) {
+        2
+    } else {
+        1
+    }
+}
+
+fn is_combining_mark(ch: char) -> bool {
+    in_ranges(COMBINING_MARKS, ch)
+}
+
+fn in_ranges(ranges: &[(char, char)], ch: char) -> bool {
+    ranges
+        .binary_search_by(|&(start, end)| {
+            if end < ch {
+                std::cmp::Ordering::Less
+            } else if start > ch {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_are_sorted_and_disjoint() {
+        for table in [tables::XID_START, tables::XID_CONTINUE].iter() {
+            for pair in table.windows(2) {
+                assert!(
+                    pair[0].0 <= pair[0].1 && pair[0].1 < pair[1].0,
+                    "{:?}",
+                    pair
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn xid_properties() {
+        for &ch in ['a', 'Z', 'ß', '変', 'न', 'Ⅻ'].iter() {
+            assert!(is_xid_start(ch) && is_xid_continue(ch), "{:?}", ch);
+        }
+
+        // Continue only: digits, connector punctuation, a virama and a spacing vowel sign
+        for &ch in ['0', '_', '\u{203F}', '\u{094D}', '\u{093E}'].iter() {
+            assert!(!is_xid_start(ch) && is_xid_continue(ch), "{:?}", ch);
+        }
+
+        for &ch in [' ', '-', '²', '€', '\u{200B}'].iter() {
+            assert!(!is_xid_start(ch) && !is_xid_continue(ch), "{:?}", ch);
+        }
+    }
+}
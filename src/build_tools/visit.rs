@@ -0,0 +1,258 @@
+use crate::build_tools::ast::*;
+
+/// Visitor walks an AST by reference. Every method defaults to its matching walk_* function, which
+/// visits the node's children, so a pass only overrides the node kinds it cares about. An override
+/// that still wants the children visited calls the walk_* function itself.
+pub trait Visitor<'ast> {
+    fn visit_program(&mut self, program: &'ast RootNode) {
+        walk_program(self, program)
+    }
+    fn visit_statement(&mut self, stmt: &'ast Statement) {
+        walk_statement(self, stmt)
+    }
+    fn visit_let_statement(&mut self, stmt: &'ast LetStatement) {
+        walk_let_statement(self, stmt)
+    }
+    fn visit_const_statement(&mut self, stmt: &'ast ConstStatement) {
+        walk_const_statement(self, stmt)
+    }
+    fn visit_return_statement(&mut self, stmt: &'ast ReturnStatement) {
+        walk_return_statement(self, stmt)
+    }
+    fn visit_expression_statement(&mut self, stmt: &'ast ExpressionStatement) {
+        walk_expression_statement(self, stmt)
+    }
+    fn visit_error_statement(&mut self, _stmt: &'ast ErrorStatement) {}
+    fn visit_block_statement(&mut self, block: &'ast BlockStatement) {
+        walk_block_statement(self, block)
+    }
+    fn visit_expression(&mut self, expr: &'ast Expression) {
+        walk_expression(self, expr)
+    }
+    fn visit_identifier(&mut self, _ident: &'ast Identifier) {}
+    fn visit_integer_literal(&mut self, _lit: &'ast IntegerLiteral) {}
+    fn visit_float_literal(&mut self, _lit: &'ast FloatLiteral) {}
+    fn visit_boolean(&mut self, _boolean: &'ast Boolean) {}
+    fn visit_string_literal(&mut self, _lit: &'ast StringLiteral) {}
+    fn visit_prefix_expression(&mut self, expr: &'ast PrefixExpression) {
+        walk_prefix_expression(self, expr)
+    }
+    fn visit_infix_expression(&mut self, expr: &'ast InfixExpression) {
+        walk_infix_expression(self, expr)
+    }
+    fn visit_postfix_expression(&mut self, expr: &'ast PostfixExpression) {
+        walk_postfix_expression(self, expr)
+    }
+    fn visit_if_expression(&mut self, expr: &'ast IfExpression) {
+        walk_if_expression(self, expr)
+    }
+    fn visit_function_literal(&mut self, lit: &'ast FunctionLiteral) {
+        walk_function_literal(self, lit)
+    }
+    fn visit_call_expression(&mut self, expr: &'ast CallExpression) {
+        walk_call_expression(self, expr)
+    }
+    fn visit_array_literal(&mut self, lit: &'ast ArrayLiteral) {
+        walk_array_literal(self, lit)
+    }
+    fn visit_hash_literal(&mut self, lit: &'ast HashLiteral) {
+        walk_hash_literal(self, lit)
+    }
+    fn visit_index_expression(&mut self, expr: &'ast IndexExpression) {
+        walk_index_expression(self, expr)
+    }
+}
+
+pub fn walk_program<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, program: &'ast RootNode) {
+    for stmt in &program.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_statement<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmt: &'ast Statement) {
+    match stmt {
+        Statement::Let(stmt) => visitor.visit_let_statement(stmt),
+        Statement::Const(stmt) => visitor.visit_const_statement(stmt),
+        Statement::Return(stmt) => visitor.visit_return_statement(stmt),
+        Statement::Expression(stmt) => visitor.visit_expression_statement(stmt),
+        Statement::Error(stmt) => visitor.visit_error_statement(stmt),
+    }
+}
+
+pub fn walk_let_statement<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    stmt: &'ast LetStatement,
+) {
+    visitor.visit_identifier(&stmt.name);
+    visitor.visit_expression(&stmt.value);
+}
+
+pub fn walk_const_statement<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    stmt: &'ast ConstStatement,
+) {
+    visitor.visit_identifier(&stmt.name);
+    visitor.visit_expression(&stmt.value);
+}
+
+pub fn walk_return_statement<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    stmt: &'ast ReturnStatement,
+) {
+    visitor.visit_expression(&stmt.return_value);
+}
+
+pub fn walk_expression_statement<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    stmt: &'ast ExpressionStatement,
+) {
+    visitor.visit_expression(&stmt.expression);
+}
+
+pub fn walk_block_statement<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    block: &'ast BlockStatement,
+) {
+    for stmt in &block.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_expression<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, expr: &'ast Expression) {
+    match expr {
+        Expression::Identifier(ident) => visitor.visit_identifier(ident),
+        Expression::Integer(lit) => visitor.visit_integer_literal(lit),
+        Expression::Float(lit) => visitor.visit_float_literal(lit),
+        Expression::Boolean(boolean) => visitor.visit_boolean(boolean),
+        Expression::String(lit) => visitor.visit_string_literal(lit),
+        Expression::Prefix(expr) => visitor.visit_prefix_expression(expr),
+        Expression::Infix(expr) => visitor.visit_infix_expression(expr),
+        Expression::Postfix(expr) => visitor.visit_postfix_expression(expr),
+        Expression::If(expr) => visitor.visit_if_expression(expr),
+        Expression::Function(lit) => visitor.visit_function_literal(lit),
+        Expression::Call(expr) => visitor.visit_call_expression(expr),
+        Expression::Array(lit) => visitor.visit_array_literal(lit),
+        Expression::Hash(lit) => visitor.visit_hash_literal(lit),
+        Expression::Index(expr) => visitor.visit_index_expression(expr),
+    }
+}
+
+pub fn walk_prefix_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    expr: &'ast PrefixExpression,
+) {
+    visitor.visit_expression(&expr.right);
+}
+
+pub fn walk_infix_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    expr: &'ast InfixExpression,
+) {
+    visitor.visit_expression(&expr.left);
+    visitor.visit_expression(&expr.right);
+}
+
+pub fn walk_postfix_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    expr: &'ast PostfixExpression,
+) {
+    visitor.visit_identifier(&expr.left);
+}
+
+pub fn walk_if_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    expr: &'ast IfExpression,
+) {
+    visitor.visit_expression(&expr.condition);
+    visitor.visit_block_statement(&expr.consequence);
+    if let Some(alternative) = &expr.alternative {
+        visitor.visit_block_statement(alternative);
+    }
+}
+
+pub fn walk_function_literal<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    lit: &'ast FunctionLiteral,
+) {
+    for param in &lit.parameters {
+        visitor.visit_identifier(param);
+    }
+    visitor.visit_block_statement(&lit.body);
+}
+
+pub fn walk_call_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    expr: &'ast CallExpression,
+) {
+    visitor.visit_expression(&expr.function);
+    for arg in &expr.arguments {
+        visitor.visit_expression(arg);
+    }
+}
+
+pub fn walk_array_literal<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    lit: &'ast ArrayLiteral,
+) {
+    for element in &lit.elements {
+        visitor.visit_expression(element);
+    }
+}
+
+pub fn walk_hash_literal<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, lit: &'ast HashLiteral) {
+    for (key, value) in &lit.pairs {
+        visitor.visit_expression(key);
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_index_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    expr: &'ast IndexExpression,
+) {
+    visitor.visit_expression(&expr.left);
+    visitor.visit_expression(&expr.index);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_tools::lexer::Lexer;
+    use crate::build_tools::parser::Parser;
+
+    /// Identifiers collects the name of every identifier visited, in the order they were visited
+    #[derive(Default)]
+    struct Identifiers<'ast> {
+        names: Vec<&'ast str>,
+    }
+
+    impl<'ast> Visitor<'ast> for Identifiers<'ast> {
+        fn visit_identifier(&mut self, ident: &'ast Identifier) {
+            self.names.push(&ident.value);
+        }
+    }
+
+    #[test]
+    fn visits_every_identifier() {
+        let source = r#"
+            let add = func(a, b) { func(c) { a + b + c } };
+            let table = {"sum": add(x, y)(z), key: [w]};
+            table["sum"][i]
+        "#;
+        let mut parser = Parser::new(Lexer::new(source));
+        let program = parser.parse_program();
+        assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+
+        let mut identifiers = Identifiers::default();
+        identifiers.visit_program(&program);
+
+        assert_eq!(identifiers.names.len(), 16);
+        assert_eq!(
+            identifiers.names,
+            [
+                "add", "a", "b", "c", "a", "b", "c", "table", "add", "x", "y", "z", "key", "w",
+                "table", "i",
+            ]
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use crate::evaluator::object::Object;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// Environment holds the bindings of a single lexical scope, which names in it are constants,
+/// and the scope enclosing it (if any) so lookups can fall through to outer scopes
+#[derive(Default)]
+pub struct Environment {
+    store: HashMap<String, Object>,
+    constants: HashSet<String>,
+    outer: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    /// new creates an empty, top level Environment
+    pub fn new() -> Environment {
+        Environment::default()
+    }
+
+    /// new_enclosed creates an empty Environment whose lookups fall back to `outer`. Used for
+    /// function calls, so a function body sees both its arguments and the scope it closed over
+    pub fn new_enclosed(outer: Rc<RefCell<Environment>>) -> Environment {
+        Environment {
+            outer: Some(outer),
+            ..Environment::default()
+        }
+    }
+
+    /// get looks up a name in this scope, then in each enclosing scope in turn
+    pub fn get(&self, name: &str) -> Option<Object> {
+        match self.store.get(name) {
+            Some(value) => Some(value.clone()),
+            None => match &self.outer {
+                Some(outer) => outer.borrow().get(name),
+                None => None,
+            },
+        }
+    }
+
+    /// set binds a name in this scope
+    pub fn set(&mut self, name: &str, value: Object) {
+        self.store.insert(name.to_owned(), value);
+    }
+
+    /// set_const binds a name in this scope and marks it as a constant
+    pub fn set_const(&mut self, name: &str, value: Object) {
+        self.constants.insert(name.to_owned());
+        self.set(name, value);
+    }
+
+    /// assign updates an existing binding in whichever scope (this one or an enclosing one) it
+    /// was defined in. Fails if the name is unbound or bound as a constant
+    pub fn assign(&mut self, name: &str, value: Object) -> Result<(), String> {
+        if self.store.contains_key(name) {
+            if self.is_const(name) {
+                return Err(format!("cannot assign to const: {}", name));
+            }
+            self.set(name, value);
+            return Ok(());
+        }
+
+        match &self.outer {
+            Some(outer) => outer.borrow_mut().assign(name, value),
+            None => Err(format!("identifier not found: {}", name)),
+        }
+    }
+
+    /// bindings returns the names bound in this scope, not enclosing ones, with their values,
+    /// sorted by name
+    pub fn bindings(&self) -> Vec<(&str, &Object)> {
+        let mut bindings: Vec<(&str, &Object)> = self
+            .store
+            .iter()
+            .map(|(name, value)| (&name[..], value))
+            .collect();
+
+        bindings.sort_by_key(|(name, _)| *name);
+        bindings
+    }
+
+    /// is_const reports whether a name is bound as a constant in this scope. Enclosing scopes
+    /// are not consulted, so inner scopes are free to shadow an outer constant
+    pub fn is_const(&self, name: &str) -> bool {
+        self.constants.contains(name)
+    }
+}
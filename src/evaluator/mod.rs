@@ -0,0 +1,630 @@
+pub mod environment;
+pub mod object;
+
+use crate::build_tools::ast;
+use environment::Environment;
+use object::{Function, Object};
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// Expressions nested deeper than this, counting the expressions inside every function being
+/// called, evaluate to an error instead of overflowing the native stack. Each level takes up to
+/// about 3 KiB of stack in a debug build, so the limit fits in the 2 MiB that spawned threads
+/// (test threads included) get by default. A recursive call takes two or three levels
+const MAX_EVAL_DEPTH: usize = 500;
+
+thread_local! {
+    /// EVAL_DEPTH counts the expressions being evaluated on this thread
+    static EVAL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// eval evaluates every statement in the program against `env` and returns the value of the
+/// last one. Evaluation stops early at a top level `return` or at the first runtime error.
+pub fn eval(program: &ast::RootNode, env: &Rc<RefCell<Environment>>) -> Object {
+    let mut result = Object::Null;
+
+    for stmt in &program.statements {
+        result = eval_statement(stmt, env);
+
+        match result {
+            Object::ReturnValue(value) => return *value,
+            Object::Error(_) => return result,
+            _ => {}
+        }
+    }
+
+    result
+}
+
+fn eval_block_stmt(block: &ast::BlockStatement, env: &Rc<RefCell<Environment>>) -> Object {
+    let mut result = Object::Null;
+
+    for stmt in &block.statements {
+        result = eval_statement(stmt, env);
+
+        // Leave return values wrapped so they keep bubbling up to the enclosing function
+        if let Object::ReturnValue(_) | Object::Error(_) = result {
+            return result;
+        }
+    }
+
+    result
+}
+
+fn eval_statement(stmt: &ast::Statement, env: &Rc<RefCell<Environment>>) -> Object {
+    match stmt {
+        ast::Statement::Expression(stmt) => eval_expr(&stmt.expression, env),
+        ast::Statement::Let(stmt) => eval_binding(&stmt.name, &stmt.value, false, env),
+        ast::Statement::Const(stmt) => eval_binding(&stmt.name, &stmt.value, true, env),
+        ast::Statement::Return(stmt) => {
+            let value = eval_expr(&stmt.return_value, env);
+            if is_error(&value) {
+                return value;
+            }
+            Object::ReturnValue(Box::new(value))
+        }
+        ast::Statement::Error(_) => {
+            new_error("cannot evaluate a statement that failed to parse".to_owned())
+        }
+    }
+}
+
+fn eval_binding(
+    name: &ast::Identifier,
+    value: &ast::Expression,
+    constant: bool,
+    env: &Rc<RefCell<Environment>>,
+) -> Object {
+    let value = eval_expr(value, env);
+    if is_error(&value) {
+        return value;
+    }
+
+    let mut env = env.borrow_mut();
+
+    if env.is_const(&name.value) {
+        return new_error(format!("cannot reassign const: {}", name.value));
+    }
+
+    if constant {
+        env.set_const(&name.value, value);
+    } else {
+        env.set(&name.value, value);
+    }
+
+    Object::Null
+}
+
+/// eval_expr evaluates an expression. Every evaluation that recurses goes through here, so this
+/// is where nesting is limited to MAX_EVAL_DEPTH
+fn eval_expr(expr: &ast::Expression, env: &Rc<RefCell<Environment>>) -> Object {
+    let depth = EVAL_DEPTH.with(Cell::get);
+    if depth >= MAX_EVAL_DEPTH {
+        return new_error("maximum recursion depth exceeded".to_owned());
+    }
+
+    EVAL_DEPTH.with(|eval_depth| eval_depth.set(depth + 1));
+    let result = eval_expr_kind(expr, env);
+    EVAL_DEPTH.with(|eval_depth| eval_depth.set(depth));
+
+    result
+}
+
+fn eval_expr_kind(expr: &ast::Expression, env: &Rc<RefCell<Environment>>) -> Object {
+    match expr {
+        ast::Expression::Integer(lit) => Object::Integer(lit.value),
+        ast::Expression::Float(lit) => Object::Float(lit.value),
+        ast::Expression::Boolean(boolean) => Object::Boolean(boolean.value),
+        ast::Expression::String(lit) => Object::String(lit.value.clone()),
+        ast::Expression::Identifier(ident) => eval_identifier(ident, env),
+        ast::Expression::Prefix(prefix) => {
+            let right = eval_expr(&prefix.right, env);
+            if is_error(&right) {
+                return right;
+            }
+            eval_prefix_expr(&prefix.operator, right)
+        }
+        ast::Expression::Infix(infix) => eval_infix_expr(infix, env),
+        ast::Expression::Postfix(postfix) => eval_postfix_expr(postfix, env),
+        ast::Expression::If(if_expr) => eval_if_expr(if_expr, env),
+        ast::Expression::Function(lit) => Object::Function(Function {
+            parameters: lit.parameters.clone(),
+            body: Rc::clone(&lit.body),
+            env: Rc::clone(env),
+        }),
+        ast::Expression::Array(array) => match eval_exprs(&array.elements, env) {
+            Ok(elements) => Object::Array(elements),
+            Err(error) => error,
+        },
+        ast::Expression::Hash(hash) => eval_hash_literal(hash, env),
+        ast::Expression::Call(call) => {
+            let function = eval_expr(&call.function, env);
+            if is_error(&function) {
+                return function;
+            }
+            match eval_exprs(&call.arguments, env) {
+                Ok(arguments) => apply_function(function, arguments),
+                Err(error) => error,
+            }
+        }
+        ast::Expression::Index(index) => {
+            let left = eval_expr(&index.left, env);
+            if is_error(&left) {
+                return left;
+            }
+            let idx = eval_expr(&index.index, env);
+            if is_error(&idx) {
+                return idx;
+            }
+            eval_index_expr(left, idx)
+        }
+    }
+}
+
+/// eval_exprs evaluates expressions left to right, stopping at the first error
+fn eval_exprs(
+    exprs: &[ast::Expression],
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Vec<Object>, Object> {
+    let mut result = vec![];
+
+    for expr in exprs {
+        let evaluated = eval_expr(expr, env);
+        if is_error(&evaluated) {
+            return Err(evaluated);
+        }
+        result.push(evaluated);
+    }
+
+    Ok(result)
+}
+
+fn eval_identifier(ident: &ast::Identifier, env: &Rc<RefCell<Environment>>) -> Object {
+    match env.borrow().get(&ident.value) {
+        Some(value) => value,
+        None => new_error(format!("identifier not found: {}", ident.value)),
+    }
+}
+
+fn eval_prefix_expr(operator: &str, right: Object) -> Object {
+    match (operator, &right) {
+        ("!", _) => Object::Boolean(!is_truthy(&right)),
+        ("-", Object::Integer(value)) => match value.checked_neg() {
+            Some(negated) => Object::Integer(negated),
+            None => new_error(format!("integer overflow: -({})", value)),
+        },
+        ("-", Object::Float(value)) => Object::Float(-value),
+        _ => new_error(format!(
+            "unknown operator: {}{}",
+            operator,
+            right.type_name()
+        )),
+    }
+}
+
+fn eval_infix_expr(infix: &ast::InfixExpression, env: &Rc<RefCell<Environment>>) -> Object {
+    let operator = &infix.operator[..];
+
+    let left = eval_expr(infix.left.as_ref(), env);
+    if is_error(&left) {
+        return left;
+    }
+
+    // && and || short circuit, so the right hand side is only evaluated when it's needed
+    match operator {
+        "&&" if !is_truthy(&left) => return Object::Boolean(false),
+        "||" if is_truthy(&left) => return Object::Boolean(true),
+        _ => {}
+    }
+
+    let right = eval_expr(infix.right.as_ref(), env);
+    if is_error(&right) {
+        return right;
+    }
+
+    match (&left, &right) {
+        (_, _) if operator == "&&" || operator == "||" => Object::Boolean(is_truthy(&right)),
+        (Object::Integer(l), Object::Integer(r)) => eval_integer_infix_expr(operator, *l, *r),
+        // An integer operand is promoted to a float when the other operand is one
+        (Object::Float(l), Object::Float(r)) => eval_float_infix_expr(operator, *l, *r),
+        (Object::Integer(l), Object::Float(r)) => eval_float_infix_expr(operator, *l as f64, *r),
+        (Object::Float(l), Object::Integer(r)) => eval_float_infix_expr(operator, *l, *r as f64),
+        (Object::String(l), Object::String(r)) => eval_string_infix_expr(operator, l, r),
+        (Object::Boolean(l), Object::Boolean(r)) if operator == "==" => Object::Boolean(l == r),
+        (Object::Boolean(l), Object::Boolean(r)) if operator == "!=" => Object::Boolean(l != r),
+        (Object::Null, Object::Null) if operator == "==" => Object::Boolean(true),
+        (Object::Null, Object::Null) if operator == "!=" => Object::Boolean(false),
+        _ if left.type_name() != right.type_name() => new_error(format!(
+            "type mismatch: {} {} {}",
+            left.type_name(),
+            operator,
+            right.type_name()
+        )),
+        _ => new_error(format!(
+            "unknown operator: {} {} {}",
+            left.type_name(),
+            operator,
+            right.type_name()
+        )),
+    }
+}
+
+/// eval_postfix_expr increments or decrements a numeric binding in the scope it was defined in.
+/// Like C, the expression evaluates to the value the binding held before it was updated
+fn eval_postfix_expr(postfix: &ast::PostfixExpression, env: &Rc<RefCell<Environment>>) -> Object {
+    let name = &postfix.left.value;
+    let value = eval_identifier(&postfix.left, env);
+
+    let updated = match (&value, &postfix.operator[..]) {
+        (Object::Error(_), _) => return value,
+        (Object::Integer(value), "++") => eval_integer_infix_expr("+", *value, 1),
+        (Object::Integer(value), "--") => eval_integer_infix_expr("-", *value, 1),
+        (Object::Float(value), "++") => Object::Float(value + 1.0),
+        (Object::Float(value), "--") => Object::Float(value - 1.0),
+        (_, operator) => {
+            return new_error(format!(
+                "unknown operator: {}{}",
+                value.type_name(),
+                operator
+            ))
+        }
+    };
+
+    if is_error(&updated) {
+        return updated;
+    }
+
+    match env.borrow_mut().assign(name, updated) {
+        Ok(()) => value,
+        Err(message) => new_error(message),
+    }
+}
+
+/// eval_integer_infix_expr checks every arithmetic operation, so a result that doesn't fit in an
+/// i64, or a division or remainder by zero, is a runtime error rather than a wrap or a panic
+fn eval_integer_infix_expr(operator: &str, left: i64, right: i64) -> Object {
+    let result = match operator {
+        "+" => left.checked_add(right),
+        "-" => left.checked_sub(right),
+        "*" => left.checked_mul(right),
+        "/" | "%" if right == 0 => return new_error("division by zero".to_owned()),
+        "/" => left.checked_div(right),
+        "%" => left.checked_rem(right),
+        _ => return eval_integer_comparison(operator, left, right),
+    };
+
+    match result {
+        Some(value) => Object::Integer(value),
+        None => new_error(format!("integer overflow: {} {} {}", left, operator, right)),
+    }
+}
+
+fn eval_integer_comparison(operator: &str, left: i64, right: i64) -> Object {
+    match operator {
+        "<" => Object::Boolean(left < right),
+        ">" => Object::Boolean(left > right),
+        "<=" => Object::Boolean(left <= right),
+        ">=" => Object::Boolean(left >= right),
+        "==" => Object::Boolean(left == right),
+        "!=" => Object::Boolean(left != right),
+        _ => new_error(format!("unknown operator: INTEGER {} INTEGER", operator)),
+    }
+}
+
+/// eval_float_infix_expr follows IEEE 754, so dividing by zero gives inf or NaN rather than an
+/// error
+fn eval_float_infix_expr(operator: &str, left: f64, right: f64) -> Object {
+    match operator {
+        "+" => Object::Float(left + right),
+        "-" => Object::Float(left - right),
+        "*" => Object::Float(left * right),
+        "/" => Object::Float(left / right),
+        "%" => Object::Float(left % right),
+        "<" => Object::Boolean(left < right),
+        ">" => Object::Boolean(left > right),
+        "<=" => Object::Boolean(left <= right),
+        ">=" => Object::Boolean(left >= right),
+        "==" => Object::Boolean(left == right),
+        "!=" => Object::Boolean(left != right),
+        _ => new_error(format!("unknown operator: FLOAT {} FLOAT", operator)),
+    }
+}
+
+fn eval_string_infix_expr(operator: &str, left: &str, right: &str) -> Object {
+    match operator {
+        "+" => Object::String(left.to_owned() + right),
+        "==" => Object::Boolean(left == right),
+        "!=" => Object::Boolean(left != right),
+        _ => new_error(format!("unknown operator: STRING {} STRING", operator)),
+    }
+}
+
+fn eval_if_expr(if_expr: &ast::IfExpression, env: &Rc<RefCell<Environment>>) -> Object {
+    let condition = eval_expr(if_expr.condition.as_ref(), env);
+    if is_error(&condition) {
+        return condition;
+    }
+
+    if is_truthy(&condition) {
+        eval_block_stmt(&if_expr.consequence, env)
+    } else if let Some(alternative) = &if_expr.alternative {
+        eval_block_stmt(alternative, env)
+    } else {
+        Object::Null
+    }
+}
+
+fn eval_hash_literal(hash: &ast::HashLiteral, env: &Rc<RefCell<Environment>>) -> Object {
+    let mut pairs = BTreeMap::new();
+
+    for (key_expr, value_expr) in &hash.pairs {
+        let key = eval_expr(key_expr, env);
+        if is_error(&key) {
+            return key;
+        }
+
+        let hash_key = match key.hash_key() {
+            Some(hash_key) => hash_key,
+            None => return new_error(format!("unusable as hash key: {}", key.type_name())),
+        };
+
+        let value = eval_expr(value_expr, env);
+        if is_error(&value) {
+            return value;
+        }
+
+        pairs.insert(hash_key, value);
+    }
+
+    Object::Hash(pairs)
+}
+
+/// apply_function calls a Function with already evaluated arguments. The body runs in a new
+/// Environment enclosed by the one the function was defined in, which is what makes closures work
+fn apply_function(function: Object, arguments: Vec<Object>) -> Object {
+    let function = match function {
+        Object::Function(function) => function,
+        other => return new_error(format!("not a function: {}", other.type_name())),
+    };
+
+    if function.parameters.len() != arguments.len() {
+        return new_error(format!(
+            "wrong number of arguments: want={}, got={}",
+            function.parameters.len(),
+            arguments.len()
+        ));
+    }
+
+    let mut extended_env = Environment::new_enclosed(Rc::clone(&function.env));
+    for (param, arg) in function.parameters.iter().zip(arguments) {
+        extended_env.set(&param.value, arg);
+    }
+
+    match eval_block_stmt(&function.body, &Rc::new(RefCell::new(extended_env))) {
+        Object::ReturnValue(value) => *value,
+        result => result,
+    }
+}
+
+fn eval_index_expr(left: Object, index: Object) -> Object {
+    match (&left, &index) {
+        (Object::Array(elements), Object::Integer(idx)) => {
+            // Out of bounds indexes evaluate to null rather than erroring
+            if *idx < 0 || *idx as usize >= elements.len() {
+                return Object::Null;
+            }
+            elements[*idx as usize].clone()
+        }
+        (Object::Hash(pairs), _) => match index.hash_key() {
+            Some(key) => pairs.get(&key).cloned().unwrap_or(Object::Null),
+            None => new_error(format!("unusable as hash key: {}", index.type_name())),
+        },
+        _ => new_error(format!(
+            "index operator not supported: {}",
+            left.type_name()
+        )),
+    }
+}
+
+/// is_truthy - null and false are falsy, every other value is truthy
+fn is_truthy(obj: &Object) -> bool {
+    !matches!(obj, Object::Null | Object::Boolean(false))
+}
+
+fn is_error(obj: &Object) -> bool {
+    matches!(obj, Object::Error(_))
+}
+
+fn new_error(message: String) -> Object {
+    Object::Error(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_tools::lexer::Lexer;
+    use crate::build_tools::parser::Parser;
+
+    /// run evaluates `source` in a fresh Environment and returns the displayed result, which
+    /// tells integers from floats and errors from values
+    fn run(source: &str) -> String {
+        let mut parser = Parser::new(Lexer::new(source));
+        let program = parser.parse_program();
+        assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+
+        eval(&program, &Rc::new(RefCell::new(Environment::new()))).to_string()
+    }
+
+    #[test]
+    fn closures_capture_their_environment() {
+        let source = "
+            let adder = func(x) { func(y) { x + y } };
+            let add_two = adder(2);
+            let x = 100;
+            add_two(3)";
+
+        assert_eq!(run(source), "5");
+    }
+
+    #[test]
+    fn return_unwraps_at_the_function_boundary() {
+        assert_eq!(run("return 10; 9"), "10");
+        assert_eq!(
+            run("if (true) { if (true) { return 10; } return 1; }"),
+            "10"
+        );
+
+        // A return only leaves the function it is in, not its caller
+        let source = "
+            let inner = func() { return 1; };
+            let outer = func() { inner(); 2 };
+            outer()";
+        assert_eq!(run(source), "2");
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        assert_eq!(run("false && missing"), "false");
+        assert_eq!(run("true || missing"), "true");
+        assert_eq!(
+            run("true && missing"),
+            "ERROR: identifier not found: missing"
+        );
+        assert_eq!(run("false || 1 == 1"), "true");
+        // && binds tighter, so this is true || (false && false)
+        assert_eq!(run("true || false && false"), "true");
+    }
+
+    #[test]
+    fn postfix_operators_update_the_binding() {
+        assert_eq!(run("let x = 1; x++"), "1");
+        assert_eq!(run("let x = 1; x++; x"), "2");
+        assert_eq!(run("let x = 1; x--; x--; x"), "-1");
+
+        // The binding is updated in the scope it was defined in
+        let source = "
+            let count = 0;
+            let increment = func() { count++; };
+            increment();
+            increment();
+            count";
+        assert_eq!(run(source), "2");
+    }
+
+    #[test]
+    fn postfix_operators_reject_consts() {
+        assert_eq!(run("const x = 1; x++"), "ERROR: cannot assign to const: x");
+        assert_eq!(
+            run("const x = 1; let f = func() { x-- }; f()"),
+            "ERROR: cannot assign to const: x"
+        );
+        assert_eq!(run("missing++"), "ERROR: identifier not found: missing");
+        assert_eq!(
+            run("let s = \"a\"; s++"),
+            "ERROR: unknown operator: STRING++"
+        );
+    }
+
+    #[test]
+    fn integers_are_promoted_to_floats() {
+        let tests = [
+            ("1 + 2.5", "3.5"),
+            ("2.5 * 2", "5.0"),
+            ("3 / 2", "1"),
+            ("3 / 2.0", "1.5"),
+            ("7 % 2.5", "2.0"),
+            ("1e3 - 1", "999.0"),
+            ("-2.5", "-2.5"),
+            ("1 == 1.0", "true"),
+            ("1 < 1.5", "true"),
+            ("let f = 1.5; f++; f", "2.5"),
+        ];
+
+        for (source, expected) in tests.iter() {
+            assert_eq!(run(source), *expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn floats_follow_ieee_754() {
+        assert_eq!(run("1.0 / 0"), "inf");
+        assert_eq!(run("-1 / 0.0"), "-inf");
+        assert_eq!(run("0.0 / 0"), "NaN");
+        assert_eq!(run("let nan = 0.0 / 0; nan == nan"), "false");
+        assert_eq!(run("{1.5: true}"), "ERROR: unusable as hash key: FLOAT");
+    }
+
+    #[test]
+    fn integer_arithmetic_is_checked() {
+        let min = "(-9223372036854775807 - 1)";
+        let tests = [
+            ("9223372036854775807", "9223372036854775807".to_owned()),
+            (min, "-9223372036854775808".to_owned()),
+            (
+                "9223372036854775807 + 1",
+                "ERROR: integer overflow: 9223372036854775807 + 1".to_owned(),
+            ),
+            (
+                "-9223372036854775807 - 2",
+                "ERROR: integer overflow: -9223372036854775807 - 2".to_owned(),
+            ),
+            (
+                "4611686018427387904 * 2",
+                "ERROR: integer overflow: 4611686018427387904 * 2".to_owned(),
+            ),
+            (
+                &format!("{} / -1", min)[..],
+                "ERROR: integer overflow: -9223372036854775808 / -1".to_owned(),
+            ),
+            (
+                &format!("-{}", min)[..],
+                "ERROR: integer overflow: -(-9223372036854775808)".to_owned(),
+            ),
+            (
+                "let x = 9223372036854775807; x++",
+                "ERROR: integer overflow: 9223372036854775807 + 1".to_owned(),
+            ),
+        ];
+
+        for (source, expected) in tests.iter() {
+            assert_eq!(run(source), *expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn integer_division_by_zero_is_an_error() {
+        assert_eq!(run("1 / 0"), "ERROR: division by zero");
+        assert_eq!(run("1 % 0"), "ERROR: division by zero");
+        assert_eq!(
+            run("let f = func(x) { 10 / x }; f(0)"),
+            "ERROR: division by zero"
+        );
+        assert_eq!(run("-7 % 3"), "-1");
+    }
+
+    #[test]
+    fn deep_recursion_is_an_error() {
+        let count = "let count = func(n) { if (n == 0) { 0 } else { 1 + count(n - 1) } };";
+
+        assert_eq!(run(&format!("{} count(100)", count)), "100");
+        assert_eq!(
+            run(&format!("{} count(5000)", count)),
+            "ERROR: maximum recursion depth exceeded"
+        );
+        // The depth is back to zero after the error, so later calls still work
+        assert_eq!(run(&format!("{} count(3)", count)), "3");
+
+        // Each call here nests many expressions, which is what the limit counts
+        let nested = "let f = func(n) {
+            if (n == 0) { 0 } else {
+                if (true) { if (true) { if (true) { [1 + [f(n - 1)][0]][0] } } }
+            }
+        };";
+        assert_eq!(
+            run(&format!("{} f(990)", nested)),
+            "ERROR: maximum recursion depth exceeded"
+        );
+    }
+}
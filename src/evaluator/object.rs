@@ -0,0 +1,125 @@
+use crate::build_tools::ast::{self, Node};
+use crate::evaluator::environment::Environment;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// Object - every value the evaluator produces is represented as an Object
+#[derive(Clone, Debug)]
+pub enum Object {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    String(String),
+    Null,
+    /// ReturnValue wraps the value of a `return` statement so it can bubble up through nested blocks
+    ReturnValue(Box<Object>),
+    /// Error holds a runtime error message. Evaluation stops as soon as one is produced
+    Error(String),
+    Function(Function),
+    Array(Vec<Object>),
+    Hash(BTreeMap<HashKey, Object>),
+}
+
+impl Object {
+    /// type_name returns the name of the Object's type, used in runtime error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Integer(_) => "INTEGER",
+            Object::Float(_) => "FLOAT",
+            Object::Boolean(_) => "BOOLEAN",
+            Object::String(_) => "STRING",
+            Object::Null => "NULL",
+            Object::ReturnValue(_) => "RETURN_VALUE",
+            Object::Error(_) => "ERROR",
+            Object::Function(_) => "FUNCTION",
+            Object::Array(_) => "ARRAY",
+            Object::Hash(_) => "HASH",
+        }
+    }
+
+    /// hash_key returns the HashKey for the Object, or None if the Object can't be used as a hash key.
+    /// Floats can't be, since NaN isn't equal to itself
+    pub fn hash_key(&self) -> Option<HashKey> {
+        match self {
+            Object::Integer(value) => Some(HashKey::Integer(*value)),
+            Object::Boolean(value) => Some(HashKey::Boolean(*value)),
+            Object::String(value) => Some(HashKey::String(value.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Object::Integer(value) => write!(f, "{}", value),
+            // Debug formatting keeps the `.0` on whole numbers, so a float never prints like an
+            // integer
+            Object::Float(value) => write!(f, "{:?}", value),
+            Object::Boolean(value) => write!(f, "{}", value),
+            Object::String(value) => write!(f, "{}", value),
+            Object::Null => write!(f, "null"),
+            Object::ReturnValue(value) => write!(f, "{}", value),
+            Object::Error(message) => write!(f, "ERROR: {}", message),
+            Object::Function(function) => write!(f, "{}", function),
+            Object::Array(elements) => {
+                let elements: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                write!(f, "[{}]", elements.join(", "))
+            }
+            Object::Hash(pairs) => {
+                let pairs: Vec<String> = pairs
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect();
+                write!(f, "{{{}}}", pairs.join(", "))
+            }
+        }
+    }
+}
+
+/// HashKey - the subset of Objects that can be used as keys in a Monkey hash
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HashKey {
+    Integer(i64),
+    Boolean(bool),
+    String(String),
+}
+
+impl fmt::Display for HashKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HashKey::Integer(value) => write!(f, "{}", value),
+            HashKey::Boolean(value) => write!(f, "{}", value),
+            HashKey::String(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Function - a closure: the function's parameters and body, plus the Environment it was defined in
+#[derive(Clone)]
+pub struct Function {
+    pub parameters: Vec<ast::Identifier>,
+    pub body: Rc<ast::BlockStatement>,
+    pub env: Rc<RefCell<Environment>>,
+}
+
+impl fmt::Debug for Function {
+    // The captured Environment can (and for recursive functions does) contain this very
+    // Function, so it is deliberately left out here
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parameters: Vec<&String> = self.parameters.iter().map(|p| &p.value).collect();
+        f.debug_struct("Function")
+            .field("parameters", &parameters)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parameters: Vec<&str> = self.parameters.iter().map(|p| &p.value[..]).collect();
+        write!(f, "func({}) {}", parameters.join(", "), self.body.string())
+    }
+}
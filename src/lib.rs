@@ -1 +1,3 @@
 pub mod build_tools;
+pub mod evaluator;
+pub mod repl;
@@ -1,22 +1,269 @@
-mod build_tools;
+use monkey_lang_rust::build_tools::ast;
+use monkey_lang_rust::build_tools::diagnostics::Diagnostic;
+use monkey_lang_rust::build_tools::dump;
+use monkey_lang_rust::build_tools::error::ParseError;
+use monkey_lang_rust::build_tools::formatter;
+use monkey_lang_rust::build_tools::lexer::Lexer;
+use monkey_lang_rust::build_tools::parser::Parser;
+use monkey_lang_rust::evaluator;
+use monkey_lang_rust::evaluator::environment::Environment;
+use monkey_lang_rust::evaluator::object::Object;
+use monkey_lang_rust::repl;
+
+use std::cell::RefCell;
+use std::env;
 use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::process;
+use std::rc::Rc;
+
+/// The program failed to lex or parse
+static EXIT_SYNTAX_ERROR: i32 = 1;
+/// The program parsed, but evaluating it produced an error
+static EXIT_RUNTIME_ERROR: i32 = 2;
+/// fmt --check found a file that isn't formatted
+static EXIT_NOT_FORMATTED: i32 = 3;
+/// The command line was invalid
+static EXIT_USAGE: i32 = 64;
+/// An input file couldn't be read, or a formatted one couldn't be written
+static EXIT_IO_ERROR: i32 = 74;
+
+static USAGE: &str = "usage: monkey <command> [arguments]
+
+commands:
+    run <file>                run a program and print its value
+    repl                      start an interactive session
+    tokens <file>             print the program's tokens
+    ast <file>                print the program's syntax tree
+    check <file>              parse the program and report any errors
+    fmt [--check] <file>...   format files in place, or with --check list unformatted ones
+
+<file> can be - to read from stdin. Running monkey with no command starts the repl.";
 
 fn main() {
-    let filename = "test_input.mo";
-    let input = fs::read_to_string(filename);
-    let input_str = match input {
-        Ok(string) => string,
-        Err(error) => panic!("Error opening file {}: {}", filename, error),
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    process::exit(run_command(&args));
+}
+
+/// run_command runs the subcommand named by the first argument and returns the exit code
+fn run_command(args: &[String]) -> i32 {
+    let command = match args.first() {
+        Some(command) => command.as_str(),
+        None => return start_repl(),
+    };
+    let rest = &args[1..];
+
+    match command {
+        "run" => with_file(rest, run),
+        "repl" if !rest.is_empty() => usage_error("repl takes no arguments"),
+        "repl" => start_repl(),
+        "tokens" => with_file(rest, tokens),
+        "ast" => with_file(rest, print_ast),
+        "check" => with_file(rest, check),
+        "fmt" => fmt(rest),
+        "help" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            0
+        }
+        "version" | "-V" | "--version" => {
+            println!("monkey {}", env!("CARGO_PKG_VERSION"));
+            0
+        }
+        _ => usage_error(&format!("unknown command `{}`", command)),
+    }
+}
+
+fn usage_error(message: &str) -> i32 {
+    eprintln!("error: {}\n\n{}", message, USAGE);
+    EXIT_USAGE
+}
+
+/// with_file reads the single file `args` names and hands its name and contents to `command`
+fn with_file(args: &[String], command: fn(&str, &str) -> i32) -> i32 {
+    let filename = match args {
+        [filename] => filename,
+        _ => return usage_error("expected exactly one file"),
     };
-    let lexer = build_tools::lexer::Lexer::new(input_str);
-    let parser = build_tools::parser::Parser::new(lexer);
-    // let program = parser::parse_program();
-    // let result = evaluate_ast(program);
 
-    println!("Hey from the end")
+    match read_source(filename) {
+        Ok(source) => command(display_name(filename), &source),
+        Err(code) => code,
+    }
+}
+
+/// read_source reads a file, or stdin if `filename` is `-`
+fn read_source(filename: &str) -> Result<String, i32> {
+    let result = if filename == "-" {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source).map(|_| source)
+    } else {
+        fs::read_to_string(filename)
+    };
+
+    result.map_err(|error| {
+        eprintln!(
+            "error: could not read {}: {}",
+            display_name(filename),
+            error
+        );
+        EXIT_IO_ERROR
+    })
+}
+
+fn display_name(filename: &str) -> &str {
+    if filename == "-" {
+        "<stdin>"
+    } else {
+        filename
+    }
 }
 
-// fn evaluate_ast(program: &ast::root_node) -> Object {
-//     let env = Object::new_environment();
-//     return evaluator::eval(program, env)
-// }
+/// parse parses `source`, rendering any errors to stderr
+fn parse(filename: &str, source: &str) -> Result<ast::RootNode, i32> {
+    let mut parser = Parser::new(Lexer::new(source));
+    let program = parser.parse_program();
+
+    if !parser.errors().is_empty() {
+        report(filename, source, parser.errors());
+        return Err(EXIT_SYNTAX_ERROR);
+    }
+
+    Ok(program)
+}
+
+fn report(filename: &str, source: &str, errors: &[ParseError]) {
+    let color = io::stderr().is_terminal();
+
+    for error in errors {
+        eprintln!(
+            "{}",
+            Diagnostic::from(error).render(filename, source, color)
+        );
+    }
+}
+
+fn run(filename: &str, source: &str) -> i32 {
+    let program = match parse(filename, source) {
+        Ok(program) => program,
+        Err(code) => return code,
+    };
+
+    let env = Rc::new(RefCell::new(Environment::new()));
+
+    match evaluator::eval(&program, &env) {
+        Object::Error(message) => {
+            eprintln!("error: {}", message);
+            EXIT_RUNTIME_ERROR
+        }
+        Object::Null => 0,
+        value => {
+            println!("{}", value);
+            0
+        }
+    }
+}
+
+fn start_repl() -> i32 {
+    let stdin = io::stdin();
+    let color = io::stdout().is_terminal();
+
+    match repl::start(stdin.lock(), io::stdout(), color) {
+        Ok(()) => 0,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            EXIT_IO_ERROR
+        }
+    }
+}
+
+/// tokens prints one token per line with the line:column it starts at. Lexing never fails, but
+/// a program with lex errors, such as a bad escape or a character the lexer doesn't recognize,
+/// exits with EXIT_SYNTAX_ERROR after the errors are reported
+fn tokens(filename: &str, source: &str) -> i32 {
+    let mut lexer = Lexer::new(source);
+
+    for token in &mut lexer {
+        println!(
+            "{}:{}\t{}\t{:?}",
+            token.span.line, token.span.column, token.token_type, token.literal
+        );
+    }
+
+    let errors: Vec<ParseError> = lexer
+        .take_errors()
+        .into_iter()
+        .map(ParseError::Lex)
+        .collect();
+    if !errors.is_empty() {
+        report(filename, source, &errors);
+        return EXIT_SYNTAX_ERROR;
+    }
+
+    0
+}
+
+fn print_ast(filename: &str, source: &str) -> i32 {
+    match parse(filename, source) {
+        Ok(program) => {
+            print!("{}", dump::dump(&program));
+            0
+        }
+        Err(code) => code,
+    }
+}
+
+fn check(filename: &str, source: &str) -> i32 {
+    match parse(filename, source) {
+        Ok(_) => 0,
+        Err(code) => code,
+    }
+}
+
+/// fmt rewrites each file in canonical style. With --check no file is written, and every file
+/// that isn't already formatted is listed instead. `-` formats stdin to stdout
+fn fmt(args: &[String]) -> i32 {
+    let check = args.iter().any(|arg| arg == "--check");
+    let filenames: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+
+    if filenames.is_empty() {
+        return usage_error("fmt expects at least one file");
+    }
+
+    let mut code = 0;
+
+    for filename in filenames {
+        let source = match read_source(filename) {
+            Ok(source) => source,
+            Err(error_code) => {
+                code = error_code;
+                continue;
+            }
+        };
+
+        let formatted = match formatter::format(&source) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                report(display_name(filename), &source, &errors);
+                code = EXIT_SYNTAX_ERROR;
+                continue;
+            }
+        };
+
+        if check {
+            if formatted != source {
+                println!("{} is not formatted", display_name(filename));
+                code = EXIT_NOT_FORMATTED;
+            }
+        } else if filename == "-" {
+            print!("{}", formatted);
+        } else if formatted != source {
+            if let Err(error) = fs::write(filename, formatted) {
+                eprintln!("error: could not write {}: {}", filename, error);
+                code = EXIT_IO_ERROR;
+            }
+        }
+    }
+
+    code
+}
@@ -0,0 +1,295 @@
+use crate::build_tools::diagnostics::Diagnostic;
+use crate::build_tools::dump;
+use crate::build_tools::error::LexError;
+use crate::build_tools::lexer::Lexer;
+use crate::build_tools::parser::Parser;
+use crate::build_tools::token::TokenType;
+use crate::evaluator;
+use crate::evaluator::environment::Environment;
+use crate::evaluator::object::Object;
+
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+static PROMPT: &str = ">> ";
+
+/// Prompt shown while an unfinished input is continued on the next line
+static CONTINUATION_PROMPT: &str = ".. ";
+
+/// Name used for the REPL's input in diagnostics
+static FILENAME: &str = "<repl>";
+
+static HELP: &str = "Enter Monkey code to evaluate it. Input with unclosed brackets, strings or
+comments continues on the next line; enter an empty line to evaluate it anyway.
+
+:tokens <code>   print the tokens of <code>
+:ast <code>      print the syntax tree of <code>
+:env             list the bindings made so far
+:reset           forget every binding
+:load <file>     evaluate a file, keeping its bindings
+:help            show this message
+:quit            leave the repl (Ctrl-D works too)";
+
+/// start reads Monkey from `input` and writes the value of each entry to `output`, until the input
+/// ends or `:quit` is entered. Bindings persist from one entry to the next. An entry that is
+/// incomplete (unclosed brackets, strings or comments) continues over the following lines.
+/// Lines starting with `:` are meta-commands, see HELP. Parse errors are rendered as
+/// diagnostics, with ANSI color if `color` is set.
+pub fn start<R: BufRead, W: Write>(mut input: R, output: W, color: bool) -> io::Result<()> {
+    let mut repl = Repl {
+        output,
+        env: Rc::new(RefCell::new(Environment::new())),
+        color,
+    };
+    let mut buffer = String::new();
+
+    loop {
+        let prompt = if buffer.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        write!(repl.output, "{}", prompt)?;
+        repl.output.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            // Leave the terminal on a fresh line after Ctrl-D
+            return writeln!(repl.output);
+        }
+
+        if buffer.is_empty() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if let Some(command) = line.trim().strip_prefix(':') {
+                if !repl.meta_command(command)? {
+                    return Ok(());
+                }
+                continue;
+            }
+        }
+
+        let blank = line.trim().is_empty();
+        buffer += &line;
+
+        if !blank && is_incomplete(&buffer) {
+            continue;
+        }
+
+        repl.eval(FILENAME, &buffer)?;
+        buffer.clear();
+    }
+}
+
+struct Repl<W: Write> {
+    output: W,
+    env: Rc<RefCell<Environment>>,
+    color: bool,
+}
+
+impl<W: Write> Repl<W> {
+    /// meta_command runs a `:` command (without the colon). Returns false if the REPL should exit
+    fn meta_command(&mut self, command: &str) -> io::Result<bool> {
+        let (name, argument) = match command.find(char::is_whitespace) {
+            Some(i) => (&command[..i], command[i..].trim()),
+            None => (command, ""),
+        };
+
+        match (name, argument) {
+            ("tokens", code) if !code.is_empty() => {
+                let mut lexer = Lexer::new(code);
+
+                for token in &mut lexer {
+                    writeln!(
+                        self.output,
+                        "{}:{}\t{}\t{:?}",
+                        token.span.line, token.span.column, token.token_type, token.literal
+                    )?;
+                }
+                for error in lexer.take_errors() {
+                    let diagnostic = Diagnostic::from(&error);
+                    writeln!(
+                        self.output,
+                        "{}",
+                        diagnostic.render(FILENAME, code, self.color)
+                    )?;
+                }
+            }
+            ("ast", code) if !code.is_empty() => {
+                let mut parser = Parser::new(Lexer::new(code));
+                let program = parser.parse_program();
+
+                if self.report_errors(FILENAME, code, &parser)? {
+                    write!(self.output, "{}", dump::dump(&program))?;
+                }
+            }
+            ("env", "") => {
+                let env = self.env.borrow();
+
+                for (name, value) in env.bindings() {
+                    let keyword = if env.is_const(name) { "const" } else { "let" };
+                    writeln!(self.output, "{} {} = {}", keyword, name, value)?;
+                }
+            }
+            ("reset", "") => self.env = Rc::new(RefCell::new(Environment::new())),
+            ("load", filename) if !filename.is_empty() => match fs::read_to_string(filename) {
+                Ok(source) => self.eval(filename, &source)?,
+                Err(error) => writeln!(self.output, "could not read {}: {}", filename, error)?,
+            },
+            ("help", "") => writeln!(self.output, "{}", HELP)?,
+            ("quit", "") | ("q", "") => return Ok(false),
+            ("tokens", _) | ("ast", _) => writeln!(self.output, "usage: :{} <code>", name)?,
+            ("load", _) => writeln!(self.output, "usage: :load <file>")?,
+            ("env", _) | ("reset", _) | ("help", _) | ("quit", _) | ("q", _) => {
+                writeln!(self.output, ":{} takes no arguments", name)?
+            }
+            _ => writeln!(self.output, "unknown command :{}, try :help", name)?,
+        }
+
+        Ok(true)
+    }
+
+    /// eval parses and evaluates `source` in the REPL's environment, writing its value, or the
+    /// diagnostics for its parse errors
+    fn eval(&mut self, filename: &str, source: &str) -> io::Result<()> {
+        let mut parser = Parser::new(Lexer::new(source));
+        let program = parser.parse_program();
+
+        if !self.report_errors(filename, source, &parser)? {
+            return Ok(());
+        }
+
+        match evaluator::eval(&program, &self.env) {
+            Object::Null => Ok(()),
+            value => writeln!(self.output, "{}", value),
+        }
+    }
+
+    /// report_errors writes a diagnostic for each of the parser's errors. Returns true if there
+    /// were none
+    fn report_errors(&mut self, filename: &str, source: &str, parser: &Parser) -> io::Result<bool> {
+        for error in parser.errors() {
+            let diagnostic = Diagnostic::from(error);
+            writeln!(
+                self.output,
+                "{}",
+                diagnostic.render(filename, source, self.color)
+            )?;
+        }
+
+        Ok(parser.errors().is_empty())
+    }
+}
+
+/// is_incomplete reports whether `source` stops partway through a construct that more input could
+/// finish: an open (, [ or {, a string without its closing quote, or an unclosed /* comment
+fn is_incomplete(source: &str) -> bool {
+    let mut lexer = Lexer::new(source);
+    let mut depth: isize = 0;
+
+    loop {
+        let token = lexer.next_token();
+
+        match token.token_type {
+            TokenType::LEFT_PAREN | TokenType::LEFT_BRACKET | TokenType::LEFT_BRACE => depth += 1,
+            TokenType::RIGHT_PAREN | TokenType::RIGHT_BRACKET | TokenType::RIGHT_BRACE => {
+                depth -= 1
+            }
+            TokenType::EOF => break,
+            _ => {}
+        }
+    }
+
+    let unterminated = lexer.take_errors().iter().any(|error| {
+        matches!(
+            error,
+            LexError::UnterminatedString { .. } | LexError::UnterminatedComment { .. }
+        )
+    });
+
+    depth > 0 || unterminated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// session runs the REPL over `input` and returns everything it wrote, prompts included
+    fn session(input: &str) -> String {
+        let mut output = vec![];
+        start(input.as_bytes(), &mut output, false).unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn bindings_persist_between_entries() {
+        assert_eq!(session("let x = 5;\nx * 2\n"), ">> >> 10\n>> \n");
+    }
+
+    #[test]
+    fn unfinished_input_continues() {
+        assert_eq!(
+            session("let f = func(x) {\nx + 1\n};\nf(1)\n"),
+            ">> .. .. >> 2\n>> \n"
+        );
+        assert_eq!(session("\"a\nb\"\n"), ">> .. a\nb\n>> \n");
+        assert_eq!(session("/* a\nb */ 1\n"), ">> .. 1\n>> \n");
+    }
+
+    #[test]
+    fn blank_line_evaluates_unfinished_input() {
+        let output = session("let x = (1\n\nx\n");
+
+        assert!(
+            output.starts_with(">> .. error: unexpected end of input"),
+            "{}",
+            output
+        );
+        assert!(
+            output.ends_with(">> ERROR: identifier not found: x\n>> \n"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn meta_commands() {
+        assert_eq!(
+            session("let x = 1;\nconst c = 2;\n:env\n"),
+            ">> >> >> const c = 2\nlet x = 1\n>> \n"
+        );
+        assert_eq!(
+            session("let x = 1;\n:reset\nx\n"),
+            ">> >> >> ERROR: identifier not found: x\n>> \n"
+        );
+        assert_eq!(
+            session(":tokens x + 1\n"),
+            ">> 1:1\tIDENTIFIER\t\"x\"\n1:3\tPLUS\t\"+\"\n1:5\tINTEGER\t\"1\"\n>> \n"
+        );
+        assert!(session(":ast 1 + 2\n").contains("InfixExpression"));
+        assert!(session(":help\n").contains(":load <file>"));
+    }
+
+    #[test]
+    fn meta_command_mistakes() {
+        assert_eq!(
+            session(":bogus\n"),
+            ">> unknown command :bogus, try :help\n>> \n"
+        );
+        assert_eq!(session(":env x\n"), ">> :env takes no arguments\n>> \n");
+        assert_eq!(session(":tokens\n"), ">> usage: :tokens <code>\n>> \n");
+        assert!(session(":load /nonexistent/file.mk\n")
+            .starts_with(">> could not read /nonexistent/file.mk: "));
+    }
+
+    #[test]
+    fn quit_stops_reading() {
+        assert_eq!(session("1\n:quit\n2\n"), ">> 1\n>> ");
+        assert_eq!(session(":q\n"), ">> ");
+    }
+}
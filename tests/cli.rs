@@ -0,0 +1,240 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// The exit codes documented in src/main.rs
+const SYNTAX_ERROR: i32 = 1;
+const RUNTIME_ERROR: i32 = 2;
+const USAGE: i32 = 64;
+const IO_ERROR: i32 = 74;
+
+const PROGRAM: &str = "let x = 1;\nx + 2";
+
+/// SourceFile is a Monkey file in the temp directory, removed again when dropped
+struct SourceFile {
+    path: PathBuf,
+}
+
+impl SourceFile {
+    fn new(name: &str, contents: &str) -> SourceFile {
+        let path =
+            std::env::temp_dir().join(format!("monkey-cli-{}-{}.mk", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+
+        SourceFile { path }
+    }
+
+    fn arg(&self) -> &str {
+        self.path.to_str().unwrap()
+    }
+}
+
+impl Drop for SourceFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// monkey runs the monkey binary with `args`, writing `stdin` to its standard input
+fn monkey(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_monkey"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn run_prints_the_value() {
+    let file = SourceFile::new("run", PROGRAM);
+    let output = monkey(&["run", file.arg()], "");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "3\n");
+    assert_eq!(stderr(&output), "");
+
+    let output = monkey(&["run", "-"], "let a = [1, 2];\na[1] * 10");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "20\n");
+}
+
+#[test]
+fn run_prints_nothing_for_null() {
+    let output = monkey(&["run", "-"], "let x = 1;");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn run_syntax_error() {
+    let file = SourceFile::new("run-syntax-error", "let x = ;");
+    let output = monkey(&["run", file.arg()], "");
+
+    assert_eq!(output.status.code(), Some(SYNTAX_ERROR));
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).contains(&format!("{}:1:9", file.arg())));
+    assert!(stderr(&output).contains("expected an expression, found SEMICOLON `;`"));
+}
+
+#[test]
+fn run_runtime_error() {
+    let file = SourceFile::new("run-runtime-error", "1 + true");
+    let output = monkey(&["run", file.arg()], "");
+
+    assert_eq!(output.status.code(), Some(RUNTIME_ERROR));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(stderr(&output), "error: type mismatch: INTEGER + BOOLEAN\n");
+
+    let output = monkey(&["run", "-"], "let f = func(x) { x / 0 };\nf(1)");
+    assert_eq!(output.status.code(), Some(RUNTIME_ERROR));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(stderr(&output), "error: division by zero\n");
+}
+
+#[test]
+fn tokens() {
+    let output = monkey(&["tokens", "-"], PROGRAM);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "1:1\tLET\t\"let\"
+1:5\tIDENTIFIER\t\"x\"
+1:7\tEQUAL\t\"=\"
+1:9\tINTEGER\t\"1\"
+1:10\tSEMICOLON\t\";\"
+2:1\tIDENTIFIER\t\"x\"
+2:3\tPLUS\t\"+\"
+2:5\tINTEGER\t\"2\"
+"
+    );
+}
+
+#[test]
+fn tokens_lex_error() {
+    let output = monkey(&["tokens", "-"], "\"a\\q\" @");
+
+    // Every token is still printed, the errors are reported after them
+    assert_eq!(output.status.code(), Some(SYNTAX_ERROR));
+    assert_eq!(
+        stdout(&output),
+        "1:1\tSTRING\t\"a\\\\q\"\n1:7\tILLEGAL\t\"@\"\n"
+    );
+    assert!(stderr(&output).contains("unknown escape sequence `\\q`"));
+    assert!(stderr(&output).contains("unexpected character `@`"));
+}
+
+#[test]
+fn ast() {
+    let file = SourceFile::new("ast", PROGRAM);
+    let output = monkey(&["ast", file.arg()], "");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "LetStatement 1:1
+  Identifier x 1:5
+  IntegerLiteral 1 1:9
+ExpressionStatement 2:1
+  InfixExpression + 2:1
+    Identifier x 2:1
+    IntegerLiteral 2 2:5
+"
+    );
+}
+
+#[test]
+fn ast_parse_error() {
+    let output = monkey(&["ast", "-"], "let = 1;");
+
+    assert_eq!(output.status.code(), Some(SYNTAX_ERROR));
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).contains("<stdin>:1:5"));
+}
+
+#[test]
+fn check() {
+    let output = monkey(&["check", "-"], PROGRAM);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn check_reports_every_error() {
+    let file = SourceFile::new("check", "let x = ;\nlet y = \"a\\q\";\nlet = 3;");
+    let output = monkey(&["check", file.arg()], "");
+
+    assert_eq!(output.status.code(), Some(SYNTAX_ERROR));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(stderr(&output).matches("error: ").count(), 3);
+}
+
+#[test]
+fn check_does_not_evaluate() {
+    let output = monkey(&["check", "-"], "1 + true");
+
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn repl() {
+    let output = monkey(&["repl"], "let x = 1;\nx + 2\n");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), ">> >> 3\n>> \n");
+}
+
+#[test]
+fn repl_is_the_default() {
+    let output = monkey(&[], "1 + 1\n");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), ">> 2\n>> \n");
+}
+
+#[test]
+fn unknown_command() {
+    let output = monkey(&["frobnicate"], "");
+
+    assert_eq!(output.status.code(), Some(USAGE));
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).starts_with("error: unknown command `frobnicate`\n\nusage: monkey"));
+}
+
+#[test]
+fn wrong_number_of_arguments() {
+    assert_eq!(monkey(&["run"], "").status.code(), Some(USAGE));
+    assert_eq!(monkey(&["ast", "a", "b"], "").status.code(), Some(USAGE));
+    assert_eq!(monkey(&["repl", "a"], "").status.code(), Some(USAGE));
+}
+
+#[test]
+fn missing_file() {
+    let output = monkey(&["run", "/nonexistent/monkey/file.mk"], "");
+
+    assert_eq!(output.status.code(), Some(IO_ERROR));
+    assert!(stderr(&output).starts_with("error: could not read /nonexistent/monkey/file.mk"));
+}
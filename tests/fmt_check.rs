@@ -0,0 +1,154 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// The exit codes documented in src/main.rs
+const SYNTAX_ERROR: i32 = 1;
+const NOT_FORMATTED: i32 = 3;
+const USAGE: i32 = 64;
+const IO_ERROR: i32 = 74;
+
+const FORMATTED: &str = "let x = 1 + 2;\n";
+const UNFORMATTED: &str = "let   x=1+2";
+
+/// SourceFile is a Monkey file in the temp directory, removed again when dropped
+struct SourceFile {
+    path: PathBuf,
+}
+
+impl SourceFile {
+    fn new(name: &str, contents: &str) -> SourceFile {
+        let path = std::env::temp_dir().join(format!(
+            "monkey-fmt-check-{}-{}.mk",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, contents).unwrap();
+
+        SourceFile { path }
+    }
+
+    fn arg(&self) -> &str {
+        self.path.to_str().unwrap()
+    }
+
+    fn contents(&self) -> String {
+        fs::read_to_string(&self.path).unwrap()
+    }
+}
+
+impl Drop for SourceFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// monkey runs the monkey binary with `args`, writing `stdin` to its standard input
+fn monkey(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_monkey"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn formatted_file_passes() {
+    let file = SourceFile::new("formatted", FORMATTED);
+    let output = monkey(&["fmt", "--check", file.arg()], "");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(file.contents(), FORMATTED);
+}
+
+#[test]
+fn unformatted_file_is_listed_and_left_alone() {
+    let file = SourceFile::new("unformatted", UNFORMATTED);
+    let output = monkey(&["fmt", "--check", file.arg()], "");
+
+    assert_eq!(output.status.code(), Some(NOT_FORMATTED));
+    assert_eq!(
+        stdout(&output),
+        format!("{} is not formatted\n", file.arg())
+    );
+    assert_eq!(file.contents(), UNFORMATTED);
+}
+
+#[test]
+fn any_unformatted_file_fails_the_check() {
+    let formatted = SourceFile::new("mixed-formatted", FORMATTED);
+    let unformatted = SourceFile::new("mixed-unformatted", UNFORMATTED);
+    let output = monkey(&["fmt", "--check", formatted.arg(), unformatted.arg()], "");
+
+    assert_eq!(output.status.code(), Some(NOT_FORMATTED));
+    assert_eq!(
+        stdout(&output),
+        format!("{} is not formatted\n", unformatted.arg())
+    );
+}
+
+#[test]
+fn check_passes_once_formatted() {
+    let file = SourceFile::new("rewrite", UNFORMATTED);
+
+    assert_eq!(monkey(&["fmt", file.arg()], "").status.code(), Some(0));
+    assert_eq!(file.contents(), FORMATTED);
+    assert_eq!(
+        monkey(&["fmt", "--check", file.arg()], "").status.code(),
+        Some(0)
+    );
+}
+
+#[test]
+fn syntax_error_fails_the_check() {
+    let file = SourceFile::new("syntax-error", "let x = ;");
+    let output = monkey(&["fmt", "--check", file.arg()], "");
+
+    assert_eq!(output.status.code(), Some(SYNTAX_ERROR));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("expected an expression"));
+}
+
+#[test]
+fn missing_file_is_an_io_error() {
+    let output = monkey(&["fmt", "--check", "/nonexistent/monkey/file.mk"], "");
+
+    assert_eq!(output.status.code(), Some(IO_ERROR));
+}
+
+#[test]
+fn no_files_is_a_usage_error() {
+    let output = monkey(&["fmt", "--check"], "");
+
+    assert_eq!(output.status.code(), Some(USAGE));
+}
+
+#[test]
+fn stdin() {
+    let output = monkey(&["fmt", "--check", "-"], UNFORMATTED);
+    assert_eq!(output.status.code(), Some(NOT_FORMATTED));
+    assert_eq!(stdout(&output), "<stdin> is not formatted\n");
+
+    let output = monkey(&["fmt", "--check", "-"], FORMATTED);
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = monkey(&["fmt", "-"], UNFORMATTED);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), FORMATTED);
+}
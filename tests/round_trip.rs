@@ -0,0 +1,110 @@
+use monkey_lang_rust::build_tools::ast::{ExpressionStatement, Node, RootNode};
+use monkey_lang_rust::build_tools::fold::{self, Folder};
+use monkey_lang_rust::build_tools::lexer::Lexer;
+use monkey_lang_rust::build_tools::parser::Parser;
+use monkey_lang_rust::build_tools::token::{Span, Token, TokenType};
+
+/// Normalize strips everything that legitimately changes when a program is printed and parsed
+/// again: spans, and the leading token of expression statements (printing adds parentheses, so
+/// `a + b;` starts with `(` once printed)
+struct Normalize;
+
+impl Folder for Normalize {
+    fn fold_span(&mut self, _span: Span) -> Span {
+        Span::default()
+    }
+
+    fn fold_expression_statement(&mut self, stmt: ExpressionStatement) -> ExpressionStatement {
+        let mut stmt = fold::fold_expression_statement(self, stmt);
+        stmt.token = Token {
+            token_type: TokenType::EOF,
+            literal: "".to_owned(),
+            span: Span::default(),
+        };
+        stmt
+    }
+}
+
+fn parse(input: &str) -> RootNode {
+    let mut parser = Parser::new(Lexer::new(input));
+    let program = parser.parse_program();
+
+    assert!(
+        parser.errors().is_empty(),
+        "failed to parse {:?}: {:?}",
+        input,
+        parser.errors()
+    );
+
+    program
+}
+
+fn assert_round_trips(input: &str) {
+    let program = parse(input);
+    let printed = program.to_string();
+    let reparsed = parse(&printed);
+
+    assert_eq!(
+        Normalize.fold_program(program),
+        Normalize.fold_program(reparsed.clone()),
+        "{:?} printed as {:?} did not parse back to the same tree",
+        input,
+        printed
+    );
+    assert_eq!(
+        printed,
+        reparsed.string(),
+        "printing is not stable for {:?}",
+        input
+    );
+}
+
+#[test]
+fn literals() {
+    assert_round_trips("5; true; false; \"hello world\"; foobar;");
+    assert_round_trips("3.14; 1e-9; 2.5E+3; 0.5 * 2 + 1;");
+    assert_round_trips("0xff; 0b1010_1010; 0o777; 1_000_000; 1_000.5e1_0;");
+    assert_round_trips("[]; [1, \"two\", [3]]; {}; {\"a\": 1, true: [2], 3: {4: 5}};");
+    assert_round_trips(r#""tab\there"; "a \"quote\" and \\"; "line\r\n\0"; "\u{1f600} \u{7}";"#);
+}
+
+#[test]
+fn operators() {
+    assert_round_trips("-a * b; !-a; !!true; a + b + c; a + b * c - d / e % f;");
+    assert_round_trips("a < b == c > d; a <= b != c >= d; a && b || !c;");
+    assert_round_trips("(a + b) * (c - d); -(5 + 5); x++; y--; x++ + y--;");
+}
+
+#[test]
+fn statements() {
+    assert_round_trips("let x = 5; const y = x * 2; return x + y;");
+    assert_round_trips("let add = func(a, b) { return a + b; }; add(1, 2 * 3);");
+    assert_round_trips("let größe = 1; const 変数 = größe * 2; café_x1 + 変数;");
+    assert_round_trips(
+        "/// One\n///\n/// Two\nlet a = 1; /// Three\nconst b = func() { /// Four\nlet c = 2; c };",
+    );
+}
+
+#[test]
+fn conditionals() {
+    assert_round_trips("if (x < y) { x }");
+    assert_round_trips("if (x) { let z = 1; z } else { y; }");
+    assert_round_trips("let max = func(a, b) { if (a > b) { a } else { b } };");
+    assert_round_trips("if (true) { } else { }");
+}
+
+#[test]
+fn calls_and_indexes() {
+    assert_round_trips("add(a + b * c, d)[0]; func(x) { x }(5); f()(); arr[1 + 1][h[\"k\"]];");
+    assert_round_trips("let counter = func() { let n = 0; func() { n++ } }; counter()();");
+}
+
+#[test]
+fn printed_form_is_fully_parenthesized() {
+    let program = parse("a + b * c; -x[1]; if (a) { b } else { c }; let f = func(x, y) { x };");
+
+    assert_eq!(
+        program.to_string(),
+        "(a + (b * c));(-(x[1]));if (a) { b; } else { c; };let f = func(x, y) { x; };"
+    );
+}